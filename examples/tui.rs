@@ -1,28 +1,419 @@
+//! Interactive Game of Life in the terminal
+//!
+//! Usage: `cargo run --example tui -- [FILE]`, or pipe a pattern to stdin.
+//! Without a pattern, starts with a blank board the size of the terminal.
+
 use std::error::Error;
-use std::io::{stdin, Read};
-use std::thread::sleep;
-use std::time::Duration;
+use std::fs;
+use std::io::{self, stdin, stdout, IsTerminal, Read, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+
+use wasm_game_of_life::{Cell, EdgeBehavior, TextOptions, TextStyle, Universe};
+
+const HELP: &str = "space play  n step  +/- speed  arrows/hjkl move  HJKL jump  \
+    enter toggle  u/r undo/redo  c clear  e edges  v view  C color  o open  q quit";
+
+const ALIVE: &str = "██";
+const DEAD: &str = " ·";
+
+/// Rows below the board for the status and help lines
+const FOOTER_ROWS: u16 = 2;
+
+/// Cells moved by `HJKL`
+const JUMP: u32 = 8;
 
-use wasm_game_of_life::{parse_str, Grid, Universe};
+/// Generations per second, halved or doubled by `-` and `+`
+const MIN_SPEED: f64 = 0.5;
+const MAX_SPEED: f64 = 64.0;
+
+const HISTORY_BUDGET: usize = 16 * 1024 * 1024;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut contents = String::new();
-    stdin().read_to_string(&mut contents)?;
-    let Grid {
-        width,
-        height,
-        cells,
-    } = parse_str(&contents).map_err(|e| {
-        eprintln!("{}", e);
-        e
-    })?;
-    let mut universe = Universe::of_cells(width.try_into()?, height.try_into()?, cells);
-
-    for _ in 0..1 {
-        println!("{}", universe);
-        universe.tick();
-        sleep(Duration::from_millis(500));
-    }
+    let universe = match std::env::args().nth(1) {
+        Some(path) => load(&path)?,
+        None if !stdin().is_terminal() => {
+            let mut contents = Vec::new();
+            stdin().read_to_end(&mut contents)?;
+            non_empty(Universe::of_file(&contents)?)?
+        }
+        None => {
+            let (cols, rows) = terminal::size()?;
+            Universe::new(
+                (cols / 2).max(1) as u32,
+                rows.saturating_sub(FOOTER_ROWS).max(1) as u32,
+            )
+        }
+    };
 
+    let mut app = App::new(universe);
+    let _guard = TerminalGuard::new()?;
+    app.run(&mut stdout())?;
     Ok(())
 }
+
+fn load(path: &str) -> Result<Universe, Box<dyn Error>> {
+    Universe::of_file(&fs::read(path)?)
+        .and_then(non_empty)
+        .map_err(|e| format!("{path}: {e}").into())
+}
+
+/// Rejects universes without cells, which have nowhere to put the cursor.
+fn non_empty(universe: Universe) -> Result<Universe, Box<dyn Error>> {
+    if universe.width() == 0 || universe.height() == 0 {
+        return Err("pattern has no cells".into());
+    }
+    Ok(universe)
+}
+
+/// Leaves raw mode and the alternate screen when dropped, even on panic.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct App {
+    universe: Universe,
+    running: bool,
+    /// Generations per second
+    speed: f64,
+    /// Cell being edited, as `(row, col)`
+    cursor: (u32, u32),
+    /// Top left cell shown, as `(row, col)`
+    scroll: (u32, u32),
+    /// Dense text style, or `None` for two columns per cell
+    style: Option<TextStyle>,
+    ansi_color: bool,
+    /// Shown instead of the help line until the next key
+    message: Option<String>,
+    /// Path being typed after `o`
+    prompt: Option<String>,
+    quit: bool,
+}
+
+impl App {
+    fn new(mut universe: Universe) -> App {
+        universe.set_history_budget(HISTORY_BUDGET);
+        App {
+            universe,
+            running: false,
+            speed: 8.0,
+            cursor: (0, 0),
+            scroll: (0, 0),
+            style: None,
+            ansi_color: false,
+            message: None,
+            prompt: None,
+            quit: false,
+        }
+    }
+
+    fn run(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let mut next_tick = Instant::now();
+        while !self.quit {
+            self.draw(out)?;
+
+            let timeout = if self.running {
+                next_tick.saturating_duration_since(Instant::now())
+            } else {
+                Duration::from_secs(3600)
+            };
+            if event::poll(timeout)? {
+                match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_key(key),
+                    // redrawn at the top of the loop
+                    _ => {}
+                }
+            }
+
+            let now = Instant::now();
+            if !self.running {
+                next_tick = now;
+            } else if now >= next_tick {
+                self.universe.tick();
+                next_tick += Duration::from_secs_f64(1.0 / self.speed);
+                // don't try to catch up after falling behind
+                if next_tick < now {
+                    next_tick = now;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if let Some(path) = &mut self.prompt {
+            match key.code {
+                KeyCode::Char(c) => path.push(c),
+                KeyCode::Backspace => {
+                    path.pop();
+                }
+                KeyCode::Enter => {
+                    let path = self.prompt.take().unwrap();
+                    self.open(&path);
+                }
+                KeyCode::Esc => self.prompt = None,
+                _ => {}
+            }
+            return;
+        }
+
+        self.message = None;
+        let (row, col) = self.cursor;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Char(' ') => self.running = !self.running,
+            KeyCode::Char('n') => {
+                self.running = false;
+                self.universe.tick();
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                self.speed = (self.speed * 2.0).min(MAX_SPEED)
+            }
+            KeyCode::Char('-') => self.speed = (self.speed / 2.0).max(MIN_SPEED),
+            KeyCode::Up | KeyCode::Char('k') => self.move_to(row.saturating_sub(1), col),
+            KeyCode::Down | KeyCode::Char('j') => self.move_to(row + 1, col),
+            KeyCode::Left | KeyCode::Char('h') => self.move_to(row, col.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Char('l') => self.move_to(row, col + 1),
+            KeyCode::Char('K') => self.move_to(row.saturating_sub(JUMP), col),
+            KeyCode::Char('J') => self.move_to(row + JUMP, col),
+            KeyCode::Char('H') => self.move_to(row, col.saturating_sub(JUMP)),
+            KeyCode::Char('L') => self.move_to(row, col + JUMP),
+            KeyCode::Enter | KeyCode::Char('x') => self.universe.toggle_cell(row, col),
+            KeyCode::Char('u') => {
+                self.running = false;
+                if !self.universe.undo() {
+                    self.message = Some("nothing to undo".to_owned());
+                }
+            }
+            KeyCode::Char('r') => {
+                self.running = false;
+                if !self.universe.redo() {
+                    self.message = Some("nothing to redo".to_owned());
+                }
+            }
+            KeyCode::Char('c') => self.universe.reset_blank(),
+            KeyCode::Char('e') => {
+                let next = match self.universe.edge_behavior() {
+                    EdgeBehavior::Wrap => EdgeBehavior::Dead,
+                    EdgeBehavior::Dead => EdgeBehavior::Alive,
+                    EdgeBehavior::Alive => EdgeBehavior::Wrap,
+                };
+                self.universe.set_edge_behavior(next);
+            }
+            KeyCode::Char('v') => {
+                self.style = match self.style {
+                    None => Some(TextStyle::HalfBlock),
+                    Some(TextStyle::HalfBlock) => Some(TextStyle::Braille),
+                    Some(_) => None,
+                }
+            }
+            KeyCode::Char('C') => self.ansi_color = !self.ansi_color,
+            KeyCode::Char('o') => {
+                self.running = false;
+                self.prompt = Some(String::new());
+            }
+            _ => {}
+        }
+    }
+
+    fn open(&mut self, path: &str) {
+        match load(path) {
+            Ok(universe) => {
+                *self = App {
+                    speed: self.speed,
+                    style: self.style,
+                    ansi_color: self.ansi_color,
+                    ..App::new(universe)
+                };
+                self.message = Some(format!("opened {path}"));
+            }
+            Err(e) => self.message = Some(e.to_string()),
+        }
+    }
+
+    /// Moves the cursor, clamped to the board.
+    fn move_to(&mut self, row: u32, col: u32) {
+        self.cursor = (
+            row.min(self.universe.height().saturating_sub(1)),
+            col.min(self.universe.width().saturating_sub(1)),
+        );
+    }
+
+    /// Number of rows and columns of cells that fit on screen.
+    fn view_size(&self, cols: u16, rows: u16) -> (u32, u32) {
+        let rows = rows.saturating_sub(FOOTER_ROWS) as u32;
+        let (rows, cols) = match self.style {
+            Some(style) => {
+                let (char_cols, char_rows) = style.cells_per_char();
+                (rows * char_rows, cols as u32 * char_cols)
+            }
+            None => (rows, cols as u32 / 2),
+        };
+        (
+            rows.min(self.universe.height()),
+            cols.min(self.universe.width()),
+        )
+    }
+
+    /// Scrolls just enough to keep the cursor on screen.
+    fn follow_cursor(&mut self, (view_h, view_w): (u32, u32)) {
+        fn follow(scroll: u32, cursor: u32, view: u32, len: u32) -> u32 {
+            let scroll = scroll.min(cursor).max((cursor + 1).saturating_sub(view));
+            scroll.min(len - view)
+        }
+        let (row, col) = self.cursor;
+        self.scroll = (
+            follow(self.scroll.0, row, view_h, self.universe.height()),
+            follow(self.scroll.1, col, view_w, self.universe.width()),
+        );
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let (cols, rows) = terminal::size()?;
+        let view = self.view_size(cols, rows);
+        self.follow_cursor(view);
+        let width = self.universe.width();
+
+        let text_rows = match self.style {
+            Some(style) => self.draw_text(out, style, view)?,
+            None => self.draw_cells(out, view)?,
+        };
+        // blank rows left by a smaller board
+        queue!(
+            out,
+            cursor::MoveTo(0, text_rows),
+            terminal::Clear(ClearType::FromCursorDown)
+        )?;
+
+        let status = format!(
+            "gen {}  pop {}  {}  {} gen/s  {}x{} {:?}  ({}, {})",
+            self.universe.generation(),
+            self.universe.population(),
+            if self.running { "running" } else { "paused" },
+            self.speed,
+            width,
+            self.universe.height(),
+            self.universe.edge_behavior(),
+            self.cursor.0,
+            self.cursor.1,
+        );
+        let footer = match (&self.prompt, &self.message) {
+            (Some(path), _) => format!("open: {path}"),
+            (None, Some(message)) => message.clone(),
+            (None, None) => HELP.to_owned(),
+        };
+        let fit = |s: &str| s.chars().take(cols as usize).collect::<String>();
+        queue!(
+            out,
+            cursor::MoveTo(0, rows.saturating_sub(2)),
+            SetAttribute(Attribute::Reverse),
+            Print(format!("{:width$}", fit(&status), width = cols as usize)),
+            SetAttribute(Attribute::NoReverse),
+            cursor::MoveTo(0, rows.saturating_sub(1)),
+            Print(fit(&footer)),
+            terminal::Clear(ClearType::UntilNewLine),
+        )?;
+        out.flush()
+    }
+
+    /// Draws cells two columns wide, returning the number of rows drawn.
+    fn draw_cells(&self, out: &mut impl Write, (view_h, view_w): (u32, u32)) -> io::Result<u16> {
+        let width = self.universe.width();
+        let cells = self.universe.get_cells();
+        let glyph = |row: u32, col: u32| match cells[(row * width + col) as usize] {
+            Cell::Alive => ALIVE,
+            Cell::Dead => DEAD,
+        };
+        for y in 0..view_h {
+            let row = self.scroll.0 + y;
+            let line = |cols: std::ops::Range<u32>| -> String {
+                cols.map(|col| glyph(row, self.scroll.1 + col)).collect()
+            };
+            queue!(out, cursor::MoveTo(0, y as u16))?;
+            if row == self.cursor.0 {
+                let x = self.cursor.1 - self.scroll.1;
+                queue!(
+                    out,
+                    Print(line(0..x)),
+                    SetAttribute(Attribute::Reverse),
+                    Print(glyph(row, self.cursor.1)),
+                    SetAttribute(Attribute::NoReverse),
+                    Print(line(x + 1..view_w)),
+                )?;
+            } else {
+                queue!(out, Print(line(0..view_w)))?;
+            }
+            queue!(out, terminal::Clear(ClearType::UntilNewLine))?;
+        }
+        Ok(view_h as u16)
+    }
+
+    /// Draws cells packed into characters, returning the number of rows drawn.
+    fn draw_text(
+        &self,
+        out: &mut impl Write,
+        style: TextStyle,
+        (view_h, view_w): (u32, u32),
+    ) -> io::Result<u16> {
+        let options = TextOptions {
+            style,
+            ansi_color: self.ansi_color,
+            ..Default::default()
+        };
+        let (row, col) = self.scroll;
+        let text = self
+            .universe
+            .render_text_region(row, col, view_w, view_h, &options);
+        let mut rows = 0;
+        for (y, line) in text.lines().enumerate() {
+            queue!(
+                out,
+                cursor::MoveTo(0, y as u16),
+                Print(line),
+                terminal::Clear(ClearType::UntilNewLine)
+            )?;
+            rows += 1;
+        }
+
+        // the character holding the cursor
+        let (char_cols, char_rows) = style.cells_per_char();
+        let y = (self.cursor.0 - row) / char_rows;
+        let x = (self.cursor.1 - col) / char_cols;
+        let cursor_text = self.universe.render_text_region(
+            row + y * char_rows,
+            col + x * char_cols,
+            char_cols,
+            char_rows,
+            &options,
+        );
+        queue!(
+            out,
+            cursor::MoveTo(x as u16, y as u16),
+            SetAttribute(Attribute::Reverse),
+            Print(cursor_text.trim_end_matches('\n')),
+            SetAttribute(Attribute::NoReverse),
+        )?;
+        Ok(rows)
+    }
+}
@@ -0,0 +1,150 @@
+//! Per-cell age and heat tracking for colourised rendering
+//!
+//! Ages count the consecutive generations a cell has been alive. Heat is set
+//! to the maximum whenever a cell changes state and decays exponentially
+//! afterwards, tracing where activity has been.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Universe};
+
+/// Heat of a cell that changed state in the last generation.
+pub const MAX_HEAT: u8 = u8::MAX;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Activity {
+    pub ages: Vec<u16>,
+    pub heat: Vec<u8>,
+}
+
+impl Activity {
+    pub fn new(cells: &[Cell]) -> Self {
+        Activity {
+            ages: cells.iter().map(|&c| c as u16).collect(),
+            heat: vec![0; cells.len()],
+        }
+    }
+
+    /// Updates ages and heat after a generation from `old` to `new` cells.
+    pub fn advance(&mut self, old: &[Cell], new: &[Cell]) {
+        let cells = old.iter().zip(new);
+        let stats = self.ages.iter_mut().zip(self.heat.iter_mut());
+        for ((&old, &new), (age, heat)) in cells.zip(stats) {
+            *age = match new {
+                Cell::Alive => age.saturating_add(1),
+                Cell::Dead => 0,
+            };
+            *heat = if old != new {
+                MAX_HEAT
+            } else {
+                (*heat as u16 * 7 / 8) as u8
+            };
+        }
+    }
+}
+
+/// Activity tracking methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Whether cell ages and heat are updated on each tick.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn track_activity(&self) -> bool {
+        self.activity.is_some()
+    }
+
+    /// Enables or disables tracking of cell ages and heat.
+    ///
+    /// Enabling starts alive cells at age 1 and all cells at no heat.
+    /// Tracking is updated by [`tick`](Self::tick) and
+    /// [`tick_delta`](Self::tick_delta) only: edits and undo do not change
+    /// ages or heat.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(setter))]
+    pub fn set_track_activity(&mut self, track: bool) {
+        self.activity = track.then(|| Activity::new(&self.cells));
+    }
+
+    /// Returns a pointer to the ages buffer.
+    ///
+    /// Each cell has a `u16` counting the generations it has been alive, laid
+    /// out like [`cells`](Self::cells). Null if tracking is disabled.
+    pub fn ages(&self) -> *const u16 {
+        self.activity
+            .as_ref()
+            .map_or(std::ptr::null(), |a| a.ages.as_ptr())
+    }
+
+    /// Returns a pointer to the heat buffer.
+    ///
+    /// Each cell has a `u8`, [`MAX_HEAT`] when the cell changed in the last
+    /// generation and decaying towards 0 while it stays the same, laid out
+    /// like [`cells`](Self::cells). Null if tracking is disabled.
+    pub fn heat(&self) -> *const u8 {
+        self.activity
+            .as_ref()
+            .map_or(std::ptr::null(), |a| a.heat.as_ptr())
+    }
+}
+
+impl Universe {
+    /// Get the ages of all cells, if tracking is enabled.
+    pub fn get_ages(&self) -> Option<&[u16]> {
+        self.activity.as_ref().map(|a| a.ages.as_slice())
+    }
+
+    /// Get the heat of all cells, if tracking is enabled.
+    pub fn get_heat(&self) -> Option<&[u8]> {
+        self.activity.as_ref().map(|a| a.heat.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blinker_ages_and_heat() {
+        let mut universe = Universe::new(5, 5);
+        universe.set_cells(&[(2, 1), (2, 2), (2, 3)]);
+        universe.set_track_activity(true);
+        assert_eq!(1, universe.get_ages().unwrap()[12]);
+
+        universe.tick();
+        let ages = universe.get_ages().unwrap();
+        let heat = universe.get_heat().unwrap();
+        // centre survives, ends die, top and bottom are born
+        assert_eq!((2, 0, 1), (ages[12], ages[11], ages[7]));
+        assert_eq!((0, MAX_HEAT, MAX_HEAT), (heat[12], heat[11], heat[7]));
+
+        universe.tick_delta();
+        let ages = universe.get_ages().unwrap();
+        assert_eq!((3, 1, 0), (ages[12], ages[11], ages[7]));
+    }
+
+    #[test]
+    fn test_heat_decays() {
+        let mut universe = Universe::new(4, 4);
+        universe.set_cells(&[(0, 0)]);
+        universe.set_track_activity(true);
+        universe.tick();
+        let mut last = universe.get_heat().unwrap()[0];
+        assert_eq!(MAX_HEAT, last);
+        for _ in 0..50 {
+            universe.tick();
+            let heat = universe.get_heat().unwrap()[0];
+            assert!(heat < last || heat == 0);
+            last = heat;
+        }
+        assert_eq!(0, last);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut universe = Universe::new(4, 4);
+        assert!(universe.get_ages().is_none());
+        universe.set_track_activity(true);
+        universe.set_track_activity(false);
+        assert!(universe.get_heat().is_none());
+        assert!(universe.ages().is_null());
+    }
+}
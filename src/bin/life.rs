@@ -0,0 +1,391 @@
+//! Headless simulation and conversion of patterns
+//!
+//! Run `life help` for usage. Files can be plaintext, RLE, snapshots, share
+//! strings or PNG and BMP images, and `-` reads standard input.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use wasm_game_of_life::{
+    write_plaintext, EdgeBehavior, Framebuffer, GifOptions, ImageImportOptions, RenderOptions,
+    RleOptions, SvgOptions, TextOptions, TextStyle, Universe, SNAPSHOT_MAGIC,
+};
+
+const USAGE: &str = "\
+usage: life <command> [options] <file>
+
+commands:
+  run      advance a pattern and write the result
+  convert  write a pattern in another format
+  info     print dimensions, population and period
+  render   draw a pattern as text or an image
+
+options:
+  -g, --generations <n>   generations to advance first (run, render)
+  -o, --output <path>     write to a file instead of standard output
+  -f, --format <format>   output format:
+                            run, convert: rle (default), cells, snapshot, share
+                            render: text (default), png, ppm, gif, svg
+  --edge <edge>           wrap, dead or alive, overriding the file's
+  --image-cell-size <n>   pixels between cells of images read, detected if not given
+  --image-offset <x,y>    top left pixel of the first cell of images read
+  --lenient               grow RLE patterns to fit cells outside their size
+                          and allow a missing `!`, as Golly does
+  --max-period <n>        generations to look for a period in (info, default 1000)
+  --style <style>         squares (default), half-block or braille (render text)
+  --color                 color text with ANSI escape codes (render text)
+  --cell-size <n>         pixels per cell (render png, ppm, gif and svg, default 4)
+  --grid-lines            draw lines between cells (render png, ppm, gif and svg)
+  --frames <n>            generations to animate (render gif, default 60)
+  --delay <n>             hundredths of a second per frame (render gif, default 10)
+  --labels <n>            number every nth row and column (render svg)
+";
+
+/// Options that take a value, and their short forms
+const VALUE_OPTIONS: &[(&str, Option<&str>)] = &[
+    ("--generations", Some("-g")),
+    ("--output", Some("-o")),
+    ("--format", Some("-f")),
+    ("--edge", None),
+    ("--image-cell-size", None),
+    ("--image-offset", None),
+    ("--max-period", None),
+    ("--style", None),
+    ("--cell-size", None),
+    ("--frames", None),
+    ("--delay", None),
+    ("--labels", None),
+];
+
+const FLAGS: &[&str] = &["--color", "--grid-lines", "--lenient"];
+
+type Result<T, E = Box<dyn Error>> = std::result::Result<T, E>;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+    let result = match command.as_deref() {
+        None | Some("help" | "-h" | "--help") => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Some(command) => Args::parse(args).and_then(|args| match command {
+            "run" => run(&args),
+            "convert" => convert(&args),
+            "info" => info(&args),
+            "render" => render(&args),
+            _ => Err(format!("unknown command {command:?}").into()),
+        }),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("life: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Arguments after the command.
+#[derive(Debug, Default)]
+struct Args {
+    files: Vec<String>,
+    values: HashMap<&'static str, String>,
+    flags: Vec<&'static str>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Args> {
+        let mut parsed = Args::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            if arg == "-" || !arg.starts_with('-') {
+                parsed.files.push(arg);
+                continue;
+            }
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (arg.as_str(), None),
+            };
+            if let Some(&(long, _)) = VALUE_OPTIONS
+                .iter()
+                .find(|&&(long, short)| name == long || Some(name) == short)
+            {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => args.next().ok_or(format!("{name} needs a value"))?,
+                };
+                parsed.values.insert(long, value);
+            } else if let Some(&flag) = FLAGS.iter().find(|&&flag| flag == name) {
+                parsed.flags.push(flag);
+            } else {
+                return Err(format!("unknown option {name}").into());
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    fn number(&self, name: &str, default: u32) -> Result<u32> {
+        match self.value(name) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("{name} must be a number, not {value:?}").into()),
+            None => Ok(default),
+        }
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(&name)
+    }
+
+    /// The only file argument.
+    fn file(&self) -> Result<&str> {
+        match self.files.as_slice() {
+            [file] => Ok(file),
+            [] => Err("missing file".into()),
+            _ => Err("expected a single file".into()),
+        }
+    }
+
+    /// Loads the file argument, applying `--edge`.
+    fn universe(&self) -> Result<Universe> {
+        self.load(self.file()?)
+    }
+
+    /// Loads a file, applying `--lenient` and `--edge`.
+    fn load(&self, path: &str) -> Result<Universe> {
+        let rle_options = RleOptions {
+            lenient: self.flag("--lenient"),
+        };
+        let mut universe =
+            load(path, &self.image_options()?, &rle_options).map_err(|e| format!("{path}: {e}"))?;
+        if let Some(edge) = self.value("--edge") {
+            universe.set_edge_behavior(match edge {
+                "wrap" => EdgeBehavior::Wrap,
+                "dead" => EdgeBehavior::Dead,
+                "alive" => EdgeBehavior::Alive,
+                _ => return Err(format!("unknown edge behavior {edge:?}").into()),
+            });
+        }
+        Ok(universe)
+    }
+
+    /// How cells are found in images, from `--image-cell-size` and
+    /// `--image-offset`.
+    fn image_options(&self) -> Result<ImageImportOptions> {
+        let cell_size = match self.value("--image-cell-size") {
+            Some(_) => Some(self.number("--image-cell-size", 0)?),
+            None => None,
+        };
+        let offset = match self.value("--image-offset") {
+            Some(offset) => Some(
+                offset
+                    .split_once(',')
+                    .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+                    .ok_or(format!("--image-offset must be x,y, not {offset:?}"))?,
+            ),
+            None => None,
+        };
+        Ok(ImageImportOptions {
+            cell_size,
+            offset,
+            ..Default::default()
+        })
+    }
+
+    /// Writes to `--output` or standard output.
+    fn write(&self, bytes: &[u8]) -> Result<()> {
+        match self.value("--output") {
+            Some(path) => fs::write(path, bytes).map_err(|e| format!("{path}: {e}"))?,
+            None => io::stdout().write_all(bytes)?,
+        }
+        Ok(())
+    }
+}
+
+/// Reads a pattern file, snapshot, share string or image.
+fn load(
+    path: &str,
+    image_options: &ImageImportOptions,
+    rle_options: &RleOptions,
+) -> Result<Universe> {
+    let bytes = if path == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        fs::read(path)?
+    };
+
+    if bytes.starts_with(SNAPSHOT_MAGIC) {
+        return Ok(Universe::from_snapshot(&bytes)?);
+    }
+    if bytes.starts_with(b"\x89PNG") || bytes.starts_with(b"BM") {
+        return Universe::of_image(&bytes, image_options);
+    }
+    let text = std::str::from_utf8(&bytes)?;
+    if rle_options.lenient && text.starts_with(['#', 'x']) {
+        Universe::of_rle(text, rle_options)
+    } else if text.starts_with(['!', '.', 'O', '#', 'x']) {
+        Universe::of_file(&bytes)
+    } else {
+        Ok(Universe::from_share_string(text.trim())?)
+    }
+}
+
+/// Encodes a universe in a `--format` for `run` and `convert`.
+fn encode(universe: &Universe, args: &Args) -> Result<Vec<u8>> {
+    Ok(match args.value("--format").unwrap_or("rle") {
+        "rle" => universe.to_rle().into_bytes(),
+        "cells" => {
+            let grid = universe.region(0, 0, universe.width(), universe.height());
+            write_plaintext(&grid).into_bytes()
+        }
+        "snapshot" => universe.to_snapshot(),
+        "share" => format!("{}\n", universe.to_share_string()).into_bytes(),
+        format => return Err(format!("unknown format {format:?}").into()),
+    })
+}
+
+fn advance(universe: &mut Universe, args: &Args) -> Result<()> {
+    for _ in 0..args.number("--generations", 0)? {
+        universe.tick();
+    }
+    Ok(())
+}
+
+fn run(args: &Args) -> Result<()> {
+    let mut universe = args.universe()?;
+    advance(&mut universe, args)?;
+    args.write(&encode(&universe, args)?)
+}
+
+fn convert(args: &Args) -> Result<()> {
+    let universe = args.universe()?;
+    args.write(&encode(&universe, args)?)
+}
+
+fn info(args: &Args) -> Result<()> {
+    let max_period = args.number("--max-period", 1000)?;
+    if args.files.is_empty() {
+        return Err("missing file".into());
+    }
+    let mut out = String::new();
+    for path in &args.files {
+        let universe = args.load(path)?;
+
+        let grid = universe.region(0, 0, universe.width(), universe.height());
+        let bounding_box = match grid.bounding_box() {
+            Some((x, y, w, h)) => format!("{w}x{h} at row {y}, column {x}"),
+            None => "empty".to_owned(),
+        };
+        let period = match universe.find_period(max_period) {
+            Some(p) if p.population == 0 => format!("dies by generation {}", p.start),
+            Some(p) if p.is_spaceship() => format!(
+                "spaceship, period {} moving ({}, {}) from generation {}",
+                p.period, p.offset.0, p.offset.1, p.start
+            ),
+            Some(p) if p.period == 1 => format!("still life from generation {}", p.start),
+            Some(p) => format!(
+                "oscillator, period {} from generation {}",
+                p.period, p.start
+            ),
+            None => format!("none within {max_period} generations"),
+        };
+
+        if args.files.len() > 1 {
+            out.push_str(&format!("{path}:\n"));
+        }
+        out.push_str(&format!(
+            "size: {}x{}\nedges: {:?}\npopulation: {}\nbounding box: {bounding_box}\nperiod: {period}\n",
+            universe.width(),
+            universe.height(),
+            universe.edge_behavior(),
+            universe.population(),
+        ));
+    }
+    args.write(out.as_bytes())
+}
+
+fn render(args: &Args) -> Result<()> {
+    let mut universe = args.universe()?;
+    advance(&mut universe, args)?;
+
+    match args.value("--format").unwrap_or("text") {
+        "text" => {
+            let style = match args.value("--style").unwrap_or("squares") {
+                "squares" => TextStyle::Squares,
+                "half-block" => TextStyle::HalfBlock,
+                "braille" => TextStyle::Braille,
+                style => return Err(format!("unknown style {style:?}").into()),
+            };
+            let text = universe.render_text(&TextOptions {
+                style,
+                ansi_color: args.flag("--color"),
+                ..Default::default()
+            });
+            args.write(text.as_bytes())
+        }
+        format @ ("png" | "ppm" | "gif" | "svg") => {
+            let options = RenderOptions {
+                cell_size: args.number("--cell-size", 4)?,
+                grid_lines: args.flag("--grid-lines"),
+                ..Default::default()
+            };
+            if format == "svg" {
+                let svg = universe.region_to_svg(
+                    0,
+                    0,
+                    universe.width(),
+                    universe.height(),
+                    &SvgOptions {
+                        render: options,
+                        label_step: args.number("--labels", 0)?,
+                        ..Default::default()
+                    },
+                );
+                return args.write(svg.as_bytes());
+            }
+            let mut framebuffer = Framebuffer::with_options(options);
+            if format == "gif" {
+                let delay = args.number("--delay", 10)?;
+                let options = GifOptions {
+                    frames: args.number("--frames", 60)?,
+                    frame_delay: delay.try_into().map_err(|_| "--delay is too large")?,
+                    crop: None,
+                };
+                return args.write(&framebuffer.record_gif(&universe, &options)?);
+            }
+            framebuffer.draw(&universe);
+            if framebuffer.width() == 0 || framebuffer.height() == 0 {
+                return Err("nothing to draw".into());
+            }
+            match format {
+                "png" => args.write(&framebuffer.to_png().ok_or("nothing to draw")?),
+                _ => args.write(&to_ppm(&framebuffer)),
+            }
+        }
+        format => Err(format!("unknown format {format:?}").into()),
+    }
+}
+
+/// Encodes a framebuffer as a binary PPM image, dropping alpha.
+fn to_ppm(framebuffer: &Framebuffer) -> Vec<u8> {
+    let mut out = format!(
+        "P6\n{} {}\n255\n",
+        framebuffer.width(),
+        framebuffer.height()
+    )
+    .into_bytes();
+    for pixel in framebuffer.as_bytes().chunks_exact(4) {
+        out.extend_from_slice(&pixel[..3]);
+    }
+    out
+}
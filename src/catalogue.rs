@@ -0,0 +1,332 @@
+//! Built-in catalogue of classic patterns
+//!
+//! Patterns are embedded from the `patterns/` directory, with their metadata
+//! listed here so it can be searched without parsing every file.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{parse_str, Grid, PasteMode, Universe};
+
+/// Dead cells kept around a pattern when it is loaded into a universe.
+const MARGIN: u32 = 8;
+
+/// Kinds of catalogued patterns.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PatternCategory {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    Gun,
+    Puffer,
+    Methuselah,
+}
+
+impl PatternCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatternCategory::StillLife => "still life",
+            PatternCategory::Oscillator => "oscillator",
+            PatternCategory::Spaceship => "spaceship",
+            PatternCategory::Gun => "gun",
+            PatternCategory::Puffer => "puffer",
+            PatternCategory::Methuselah => "methuselah",
+        }
+    }
+}
+
+/// A pattern of the [`CATALOGUE`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CatalogueEntry {
+    pub name: &'static str,
+    pub category: PatternCategory,
+    /// Generations per cycle, or for guns between emitted spaceships
+    pub period: Option<u32>,
+    pub discoverer: Option<&'static str>,
+    pub year: Option<u16>,
+    pub description: &'static str,
+    /// Contents of the pattern file
+    pub file: &'static str,
+}
+
+macro_rules! entry {
+    ($name:expr, $category:ident, $period:expr, $discoverer:expr, $year:expr, $file:expr, $description:expr $(,)?) => {
+        CatalogueEntry {
+            name: $name,
+            category: PatternCategory::$category,
+            period: $period,
+            discoverer: $discoverer,
+            year: $year,
+            description: $description,
+            file: include_pattern!($file),
+        }
+    };
+}
+
+/// Every catalogued pattern, grouped by category.
+#[rustfmt::skip]
+pub static CATALOGUE: &[CatalogueEntry] = &[
+    entry!("Block", StillLife, Some(1), None, None, "block.rle",
+        "The most common still life."),
+    entry!("Beehive", StillLife, Some(1), None, None, "beehive.rle",
+        "The second most common still life."),
+    entry!("Loaf", StillLife, Some(1), None, None, "loaf.rle",
+        "A common seven cell still life."),
+    entry!("Boat", StillLife, Some(1), None, None, "boat.rle",
+        "The only five cell still life."),
+    entry!("Tub", StillLife, Some(1), None, None, "tub.rle",
+        "A four cell still life."),
+    entry!("Ship", StillLife, Some(1), None, None, "ship.rle",
+        "A six cell still life."),
+    entry!("Pond", StillLife, Some(1), None, None, "pond.rle",
+        "An eight cell still life."),
+    entry!("Blinker", Oscillator, Some(2), Some("John Conway"), Some(1970), "blinker.rle",
+        "The smallest and most common oscillator."),
+    entry!("Toad", Oscillator, Some(2), Some("Simon Norton"), Some(1970), "toad.rle",
+        "The second most common oscillator."),
+    entry!("Beacon", Oscillator, Some(2), Some("John Conway"), Some(1970), "beacon.rle",
+        "Two diagonal blocks whose inner corners blink."),
+    entry!("Pulsar", Oscillator, Some(3), Some("John Conway"), Some(1970), "pulsar.rle",
+        "The most common period 3 oscillator."),
+    entry!("Figure eight", Oscillator, Some(8), Some("Simon Norton"), Some(1970), "figureeight.rle",
+        "A period 8 oscillator made of two diagonal 3 by 3 squares."),
+    entry!("Kok's galaxy", Oscillator, Some(8), Some("Jan Kok"), Some(1971), "koksgalaxy.rle",
+        "A period 8 oscillator of four interlocking bars."),
+    entry!("Pentadecathlon", Oscillator, Some(15), Some("John Conway"), Some(1970), "pentadecathlon.rle",
+        "A period 15 oscillator that evolves from a row of ten cells."),
+    entry!("P43 glider loop", Oscillator, Some(43), Some("Mike Playle"), Some(2013), "p43gliderloop.rle",
+        "A glider bouncing between four stable reflectors."),
+    entry!("Glider", Spaceship, Some(4), Some("Richard K. Guy"), Some(1970), "glider.cells",
+        "The smallest spaceship, moving diagonally at c/4."),
+    entry!("Lightweight spaceship", Spaceship, Some(4), Some("John Conway"), Some(1970), "lwss.rle",
+        "The smallest orthogonal spaceship, moving at c/2."),
+    entry!("Middleweight spaceship", Spaceship, Some(4), Some("John Conway"), Some(1970), "mwss.rle",
+        "An orthogonal c/2 spaceship."),
+    entry!("Heavyweight spaceship", Spaceship, Some(4), Some("John Conway"), Some(1970), "hwss.rle",
+        "The largest of the c/2 spaceships that can travel alone."),
+    entry!("Copperhead", Spaceship, Some(10), Some("zdr"), Some(2016), "copperhead.rle",
+        "The first c/10 orthogonal spaceship."),
+    entry!("Gosper glider gun", Gun, Some(30), Some("Bill Gosper"), Some(1970), "gosperglidergun.rle",
+        "The first known gun, emitting a glider every 30 generations."),
+    entry!("Simkin glider gun", Gun, Some(120), Some("Michael Simkin"), Some(2015), "simkinglidergun.rle",
+        "A period 120 glider gun with the smallest known starting population."),
+    entry!("Puffer train", Puffer, None, Some("Bill Gosper"), Some(1971), "puffertrain.rle",
+        "Two lightweight spaceships escorting a reaction that leaves debris behind."),
+    entry!("R-pentomino", Methuselah, None, Some("John Conway"), Some(1970), "rpentomino.rle",
+        "A five cell methuselah that stabilizes after 1103 generations."),
+    entry!("Diehard", Methuselah, None, None, None, "diehard.rle",
+        "A methuselah that vanishes completely after 130 generations."),
+    entry!("Acorn", Methuselah, None, Some("Charles Corderman"), Some(1971), "acorn.rle",
+        "A seven cell methuselah that takes 5206 generations to stabilize."),
+    entry!("Pi-heptomino", Methuselah, None, None, None, "piheptomino.rle",
+        "A common heptomino that stabilizes after 173 generations."),
+    entry!("B-heptomino", Methuselah, None, None, None, "bheptomino.rle",
+        "A heptomino that appears in many spaceship and puffer reactions."),
+];
+
+impl CatalogueEntry {
+    /// The pattern's cells.
+    pub fn grid(&self) -> Grid {
+        parse_str(self.file).expect("catalogue patterns are valid")
+    }
+
+    /// Creates a universe of at least `width` by `height` cells with the
+    /// pattern in the middle, enlarged to leave a margin around it.
+    pub fn universe(&self, width: u32, height: u32) -> Universe {
+        let grid = self.grid();
+        let width = width.max(grid.width as u32 + 2 * MARGIN);
+        let height = height.max(grid.height as u32 + 2 * MARGIN);
+        let mut universe = Universe::new(width, height);
+        let row = (height - grid.height as u32) / 2;
+        let col = (width - grid.width as u32) / 2;
+        universe.paste(&grid, row as i32, col as i32, PasteMode::Overwrite);
+        universe.clear_history();
+        universe
+    }
+
+    /// Whether every word of the query is in the name, category or
+    /// discoverer, ignoring case.
+    fn matches(&self, query: &str) -> bool {
+        let haystack = format!(
+            "{} {} {}",
+            self.name,
+            self.category.name(),
+            self.discoverer.unwrap_or_default()
+        )
+        .to_lowercase();
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| haystack.contains(word))
+    }
+}
+
+/// Catalogued patterns matching a query, in catalogue order. Every pattern
+/// matches an empty query.
+pub fn search_catalogue(query: &str) -> impl Iterator<Item = &'static CatalogueEntry> + '_ {
+    CATALOGUE.iter().filter(move |entry| entry.matches(query))
+}
+
+/// Handle to a [`CATALOGUE`] entry for JS.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct CataloguePattern {
+    index: usize,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl CataloguePattern {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.entry().name.to_owned()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn category(&self) -> PatternCategory {
+        self.entry().category
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn category_name(&self) -> String {
+        self.entry().category.name().to_owned()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn period(&self) -> Option<u32> {
+        self.entry().period
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn discoverer(&self) -> Option<String> {
+        self.entry().discoverer.map(str::to_owned)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn year(&self) -> Option<u16> {
+        self.entry().year
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn description(&self) -> String {
+        self.entry().description.to_owned()
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl CataloguePattern {
+    fn entry(&self) -> &'static CatalogueEntry {
+        &CATALOGUE[self.index]
+    }
+}
+
+/// Catalogued patterns matching a query, see [`search_catalogue`].
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn catalogue(query: &str) -> Vec<CataloguePattern> {
+    (0..CATALOGUE.len())
+        .filter(|&index| CATALOGUE[index].matches(query))
+        .map(|index| CataloguePattern { index })
+        .collect()
+}
+
+/// Catalogue methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Replaces the universe with a catalogued pattern in the middle, keeping
+    /// the dimensions unless the pattern needs more room.
+    #[cfg(feature = "wasm")]
+    pub fn reset_from_catalogue(&mut self, pattern: &CataloguePattern) {
+        let universe = pattern.entry().universe(self.width, self.height);
+        self.replace_with(universe);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::EdgeBehavior;
+    use std::collections::HashSet;
+
+    fn universe(entry: &CatalogueEntry) -> Universe {
+        let mut universe = entry.universe(0, 0);
+        universe.set_edge_behavior(EdgeBehavior::Dead);
+        universe
+    }
+
+    #[test]
+    fn test_entries() {
+        let mut names = HashSet::new();
+        for entry in CATALOGUE {
+            assert!(names.insert(entry.name), "{} is listed twice", entry.name);
+            let grid = entry.grid();
+            assert!(grid.bounding_box().is_some(), "{} is empty", entry.name);
+        }
+    }
+
+    #[test]
+    fn test_periods() {
+        for entry in CATALOGUE {
+            let expected_offset = |offset: (i64, i64)| match entry.category {
+                PatternCategory::StillLife | PatternCategory::Oscillator => offset == (0, 0),
+                _ => offset != (0, 0),
+            };
+            match (entry.category, entry.period) {
+                (
+                    PatternCategory::StillLife
+                    | PatternCategory::Oscillator
+                    | PatternCategory::Spaceship,
+                    Some(period),
+                ) => {
+                    let found = universe(entry).find_period(period).unwrap();
+                    assert_eq!(period, found.period, "{}", entry.name);
+                    assert!(expected_offset(found.offset), "{}", entry.name);
+                }
+                (PatternCategory::Gun, Some(period)) => {
+                    // a glider more every period, once the first is out
+                    let mut universe = entry.universe(200, 200);
+                    universe.set_edge_behavior(EdgeBehavior::Dead);
+                    let mut populations = Vec::new();
+                    for generation in 0..=period * 3 {
+                        if generation % period == 0 {
+                            populations.push(universe.population());
+                        }
+                        universe.tick();
+                    }
+                    assert_eq!(5, populations[3] - populations[2], "{}", entry.name);
+                    assert_eq!(5, populations[2] - populations[1], "{}", entry.name);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_diehard() {
+        let entry = search_catalogue("diehard").next().unwrap();
+        let mut universe = entry.universe(64, 64);
+        universe.set_edge_behavior(EdgeBehavior::Dead);
+        for _ in 0..129 {
+            universe.tick();
+        }
+        assert!(universe.population() > 0);
+        universe.tick();
+        assert_eq!(0, universe.population());
+    }
+
+    #[test]
+    fn test_search() {
+        let names = |query| search_catalogue(query).map(|e| e.name).collect::<Vec<_>>();
+        assert_eq!(CATALOGUE.len(), search_catalogue("").count());
+        assert_eq!(vec!["Gosper glider gun", "Puffer train"], names("GOSPER"));
+        assert_eq!(vec!["Simkin glider gun"], names("glider gun simkin"));
+        assert_eq!(4, names("oscillator conway").len());
+        assert!(names("no such pattern").is_empty());
+
+        let universe = CATALOGUE[0].universe(64, 32);
+        assert_eq!((64, 32), (universe.width(), universe.height()));
+        assert_eq!(4, universe.population());
+    }
+}
@@ -0,0 +1,172 @@
+//! Drawing lines, rectangles and brush strokes into a universe
+//!
+//! Each call is a single edit: changed cells go to the delta buffers and are
+//! recorded in the history together. Shapes past the edges wrap around or are
+//! clipped like [`Universe::paste`].
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Universe};
+
+/// Cells on the line between two points, both included, as `(row, col)`.
+///
+/// Uses Bresenham's algorithm, so consecutive cells always touch, at least
+/// diagonally.
+pub fn line_cells(from: (i64, i64), to: (i64, i64)) -> impl Iterator<Item = (i64, i64)> {
+    let (mut row, mut col) = from;
+    let d_row = -(to.0 - row).abs();
+    let d_col = (to.1 - col).abs();
+    let step_row = if row < to.0 { 1 } else { -1 };
+    let step_col = if col < to.1 { 1 } else { -1 };
+    let mut error = d_col + d_row;
+    let mut done = false;
+
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let cell = (row, col);
+        if cell == to {
+            done = true;
+        }
+        let e2 = 2 * error;
+        if e2 >= d_row {
+            error += d_row;
+            col += step_col;
+        }
+        if e2 <= d_col {
+            error += d_col;
+            row += step_row;
+        }
+        Some(cell)
+    })
+}
+
+/// Drawing methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Sets the cells on a line from `row0`, `col0` to `row1`, `col1`.
+    pub fn draw_line(&mut self, row0: i32, col0: i32, row1: i32, col1: i32, state: Cell) {
+        let line = line_cells((row0 as i64, col0 as i64), (row1 as i64, col1 as i64));
+        self.draw_cells(line, state);
+    }
+
+    /// Sets the cells of a rectangle, or only its outline if `filled` is
+    /// false.
+    pub fn draw_rect(
+        &mut self,
+        row: i32,
+        col: i32,
+        width: u32,
+        height: u32,
+        state: Cell,
+        filled: bool,
+    ) {
+        let (row, col) = (row as i64, col as i64);
+        let (bottom, right) = (row + height as i64 - 1, col + width as i64 - 1);
+        let cells = (row..=bottom).flat_map(move |r| {
+            (col..=right)
+                .filter(move |&c| filled || r == row || r == bottom || c == col || c == right)
+                .map(move |c| (r, c))
+        });
+        self.draw_cells(cells, state);
+    }
+
+    /// Sets the cells under a brush dragged through a list of points.
+    ///
+    /// `points` holds flattened `(row, col)` pairs; consecutive points are
+    /// joined with lines. The brush is a square of `size` cells centred on
+    /// the line, extending further down and right for even sizes.
+    pub fn draw_stroke(&mut self, points: &[i32], size: u32, state: Cell) {
+        let points: Vec<_> = points
+            .chunks_exact(2)
+            .map(|p| (p[0] as i64, p[1] as i64))
+            .collect();
+        let first = points.first().map(|&p| (p, p));
+        let segments = first
+            .into_iter()
+            .chain(points.windows(2).map(|w| (w[0], w[1])));
+
+        let size = size.max(1) as i64;
+        let offset = (size - 1) / 2;
+        let cells = segments
+            .flat_map(|(from, to)| line_cells(from, to))
+            .flat_map(|(row, col)| {
+                (0..size).flat_map(move |dr| {
+                    (0..size).map(move |dc| (row - offset + dr, col - offset + dc))
+                })
+            });
+        self.draw_cells(cells, state);
+    }
+}
+
+impl Universe {
+    /// Sets cells that may be past the edges as a single edit.
+    fn draw_cells(&mut self, cells: impl IntoIterator<Item = (i64, i64)>, state: Cell) {
+        let changes: Vec<_> = cells
+            .into_iter()
+            .filter_map(|(row, col)| self.edge_index(row, col))
+            .map(|idx| (idx, state))
+            .collect();
+        self.edit_cells(changes);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::EdgeBehavior;
+
+    fn drawn(universe: &Universe) -> String {
+        universe
+            .region(0, 0, universe.width(), universe.height())
+            .to_string()
+    }
+
+    #[test]
+    fn test_line_cells() {
+        let line: Vec<_> = line_cells((0, 0), (2, 5)).collect();
+        assert_eq!(vec![(0, 0), (0, 1), (1, 2), (1, 3), (2, 4), (2, 5)], line);
+        let back: Vec<_> = line_cells((2, 5), (0, 0)).collect();
+        assert_eq!(6, back.len());
+        assert_eq!(vec![(3, 3)], line_cells((3, 3), (3, 3)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_draw_line_deltas() {
+        let mut universe = Universe::new(5, 3);
+        universe.draw_line(0, 0, 2, 4, Cell::Alive);
+        assert_eq!("O....\n.OO..\n...OO\n", drawn(&universe));
+        assert_eq!(5, universe.cells_born_count());
+
+        universe.draw_line(2, 4, 0, 4, Cell::Dead);
+        assert_eq!(1, universe.cells_died_count());
+    }
+
+    #[test]
+    fn test_draw_rect() {
+        let mut universe = Universe::new(5, 4);
+        universe.draw_rect(0, 0, 4, 3, Cell::Alive, false);
+        assert_eq!("OOOO.\nO..O.\nOOOO.\n.....\n", drawn(&universe));
+        universe.draw_rect(1, 1, 2, 1, Cell::Alive, true);
+        assert_eq!("OOOO.\nOOOO.\nOOOO.\n.....\n", drawn(&universe));
+    }
+
+    #[test]
+    fn test_draw_stroke_clips() {
+        let mut universe = Universe::new(6, 4);
+        universe.set_edge_behavior(EdgeBehavior::Dead);
+        universe.draw_stroke(&[0, 0, 0, 4, 3, 4], 2, Cell::Alive);
+        assert_eq!("OOOOOO\nOOOOOO\n....OO\n....OO\n", drawn(&universe));
+    }
+
+    #[test]
+    fn test_draw_stroke_single_edit() {
+        let mut universe = Universe::new(6, 6);
+        universe.set_history_budget(1 << 20);
+        universe.draw_stroke(&[1, 1, 4, 4], 1, Cell::Alive);
+        assert!(universe.undo());
+        assert!(universe.get_cells().iter().all(|&c| c == Cell::Dead));
+    }
+}
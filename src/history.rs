@@ -0,0 +1,291 @@
+//! Undo/redo and rewindable history of universe states
+//!
+//! Every change to a [`Universe`](crate::Universe) is stored as the set of
+//! cell indices that flipped state. Since flipping is its own inverse, the
+//! same entry is used to undo and redo a change. Every
+//! [`KEYFRAME_INTERVAL`] entries a bit-packed copy of the whole universe is
+//! kept as well, so that seeking long distances through the timeline does not
+//! need to replay every entry in between.
+
+use std::{collections::VecDeque, mem};
+
+use crate::Cell;
+
+/// Number of entries between bit-packed snapshots of the universe.
+const KEYFRAME_INTERVAL: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EntryKind {
+    /// Cells changed by the user
+    Edit,
+    /// Cells changed by advancing a generation
+    Tick,
+}
+
+#[derive(Debug)]
+pub(crate) struct Entry {
+    pub kind: EntryKind,
+    /// Indices of cells whose state changed
+    pub flipped: Vec<u32>,
+    /// Generation count after the entry is applied
+    pub generation: u32,
+    /// Bit-packed cells after the entry is applied
+    keyframe: Option<Vec<u8>>,
+}
+
+impl Entry {
+    fn size(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.flipped.len() * mem::size_of::<u32>()
+            + self.keyframe.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// Timeline of changes to a universe.
+///
+/// Entries before the cursor have been applied, entries after it have been
+/// undone and can be redone.
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    entries: VecDeque<Entry>,
+    cursor: usize,
+    /// Maximum number of bytes used by entries; 0 disables recording
+    budget: usize,
+    size: usize,
+    /// Entries recorded since the last keyframe
+    since_keyframe: usize,
+}
+
+/// How to get from the cursor to a position in the timeline.
+pub(crate) enum Route<'a> {
+    /// Restore the keyframe, then replay entries forwards
+    Keyframe(&'a [u8]),
+    /// Replay entries from the current position
+    Replay,
+}
+
+impl History {
+    pub fn is_enabled(&self) -> bool {
+        self.budget > 0
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Sets the memory budget, dropping the oldest entries if it is exceeded.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.enforce_budget();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn position(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.cursor = 0;
+        self.size = 0;
+        self.since_keyframe = 0;
+    }
+
+    /// Adds an entry at the cursor, discarding any undone entries.
+    ///
+    /// `cells` is the state of the universe after the change.
+    pub fn record(&mut self, kind: EntryKind, flipped: Vec<u32>, generation: u32, cells: &[Cell]) {
+        if !self.is_enabled() || flipped.is_empty() && kind == EntryKind::Edit {
+            return;
+        }
+
+        for dropped in self.entries.drain(self.cursor..) {
+            self.size -= dropped.size();
+        }
+
+        self.since_keyframe += 1;
+        let keyframe = (self.since_keyframe >= KEYFRAME_INTERVAL).then(|| {
+            self.since_keyframe = 0;
+            pack_cells(cells)
+        });
+
+        let entry = Entry {
+            kind,
+            flipped,
+            generation,
+            keyframe,
+        };
+        self.size += entry.size();
+        self.entries.push_back(entry);
+        self.cursor = self.entries.len();
+
+        self.enforce_budget();
+    }
+
+    /// Moves the cursor back one entry, returning the entry to revert.
+    pub fn undo(&mut self) -> Option<&Entry> {
+        if self.cursor == 0 {
+            return None;
+        }
+        self.cursor -= 1;
+        self.entries.get(self.cursor)
+    }
+
+    /// Moves the cursor forward one entry, returning the entry to apply.
+    pub fn redo(&mut self) -> Option<&Entry> {
+        let entry = self.entries.get(self.cursor)?;
+        self.cursor += 1;
+        Some(entry)
+    }
+
+    pub fn kind(&self, index: usize) -> Option<EntryKind> {
+        self.entries.get(index).map(|e| e.kind)
+    }
+
+    /// Generation count at a position in the timeline.
+    pub fn generation_at(&self, position: usize) -> Option<u32> {
+        match position {
+            0 => self.entries.front().map(|e| match e.kind {
+                EntryKind::Tick => e.generation.saturating_sub(1),
+                EntryKind::Edit => e.generation,
+            }),
+            p => self.entries.get(p - 1).map(|e| e.generation),
+        }
+    }
+
+    /// Picks the cheapest way to seek to `position`, moving the cursor to the
+    /// start of the route.
+    ///
+    /// After restoring a keyframe (if any), the caller should step with
+    /// [`undo`](Self::undo) or [`redo`](Self::redo) until the cursor reaches
+    /// `position`.
+    pub fn route_to(&mut self, position: usize) -> Route<'_> {
+        let position = position.min(self.entries.len());
+        let distance = self.cursor.abs_diff(position);
+
+        // keyframe of entry `i` is the state at position `i + 1`
+        let nearest = self
+            .entries
+            .iter()
+            .enumerate()
+            .take(position)
+            .rev()
+            .find(|(_, e)| e.keyframe.is_some())
+            .map(|(i, _)| i + 1);
+
+        match nearest {
+            Some(k) if position - k + KEYFRAME_INTERVAL < distance => {
+                self.cursor = k;
+                Route::Keyframe(self.entries[k - 1].keyframe.as_deref().unwrap())
+            }
+            _ => Route::Replay,
+        }
+    }
+
+    fn enforce_budget(&mut self) {
+        while self.size > self.budget {
+            let Some(dropped) = self.entries.pop_front() else {
+                break;
+            };
+            self.size -= dropped.size();
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+    }
+}
+
+/// Packs cells into a bitset, 8 cells per byte.
+pub(crate) fn pack_cells(cells: &[Cell]) -> Vec<u8> {
+    cells
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (i, &cell)| byte | (cell as u8) << i)
+        })
+        .collect()
+}
+
+/// Unpacks `len` cells from a bitset created by [`pack_cells`].
+pub(crate) fn unpack_cells(packed: &[u8], len: usize) -> impl Iterator<Item = Cell> + '_ {
+    (0..len).map(|i| match packed[i / 8] >> (i % 8) & 1 {
+        0 => Cell::Dead,
+        _ => Cell::Alive,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn history(budget: usize) -> History {
+        let mut history = History::default();
+        history.set_budget(budget);
+        history
+    }
+
+    #[test]
+    fn test_pack_roundtrip() {
+        let cells: Vec<_> = (0..21)
+            .map(|i| if i % 3 == 0 { Cell::Alive } else { Cell::Dead })
+            .collect();
+        let packed = pack_cells(&cells);
+        assert_eq!(3, packed.len());
+        assert_eq!(
+            cells,
+            unpack_cells(&packed, cells.len()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut history = history(0);
+        history.record(EntryKind::Edit, vec![1], 0, &[]);
+        assert_eq!(0, history.len());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut history = history(1 << 20);
+        history.record(EntryKind::Edit, vec![1], 0, &[]);
+        history.record(EntryKind::Tick, vec![2, 3], 1, &[]);
+        assert_eq!(2, history.position());
+
+        assert_eq!(Some(EntryKind::Tick), history.undo().map(|e| e.kind));
+        assert_eq!(Some(EntryKind::Edit), history.undo().map(|e| e.kind));
+        assert!(history.undo().is_none());
+        assert_eq!(Some(vec![1]), history.redo().map(|e| e.flipped.clone()));
+
+        // recording discards undone entries
+        history.record(EntryKind::Edit, vec![4], 0, &[]);
+        assert_eq!(2, history.len());
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn test_budget_drops_oldest() {
+        let entry_size = mem::size_of::<Entry>() + mem::size_of::<u32>();
+        let mut history = history(entry_size * 3);
+        for i in 0..5 {
+            history.record(EntryKind::Edit, vec![i], 0, &[]);
+        }
+        assert_eq!(3, history.len());
+        assert_eq!(3, history.position());
+        while history.undo().is_some() {}
+        assert_eq!(Some(vec![2]), history.redo().map(|e| e.flipped.clone()));
+    }
+
+    #[test]
+    fn test_generation_at() {
+        let mut history = history(1 << 20);
+        history.record(EntryKind::Tick, vec![0], 5, &[]);
+        history.record(EntryKind::Edit, vec![0], 5, &[]);
+        history.record(EntryKind::Tick, vec![0], 6, &[]);
+        assert_eq!(Some(4), history.generation_at(0));
+        assert_eq!(Some(5), history.generation_at(2));
+        assert_eq!(Some(6), history.generation_at(3));
+    }
+}
@@ -0,0 +1,760 @@
+//! Importing patterns from images
+//!
+//! PNG and uncompressed BMP images are decoded to grayscale, thresholded to
+//! alive and dead pixels and sampled at the middle of each cell. The cell
+//! pitch and grid lines are detected from the lengths of runs of same
+//! colored pixels: without grid lines every run spans a whole number of
+//! cells, and with 1 pixel grid lines alive runs are 1 pixel short of that
+//! and dead runs 1 pixel over.
+
+#[cfg(feature = "wasm")]
+use js_sys::TypeError;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{prelude::*, JsValue};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+
+use crate::{Cell, Grid, Universe};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// Fraction of runs that must fit a cell pitch for it to be detected
+const MIN_PITCH_SCORE: f64 = 0.8;
+
+/// Largest image accepted, in pixels
+const MAX_PIXELS: u64 = 1 << 28;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// Neither a PNG nor a BMP image
+    UnknownFormat,
+    /// A valid image using a feature that is not supported
+    Unsupported(&'static str),
+    /// Ended before all pixels were read
+    Truncated,
+    /// A field has an impossible value
+    Invalid(&'static str),
+    /// No cells could be detected, or none fit in the image
+    NoCells,
+}
+
+impl Error for ImageError {}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::UnknownFormat => write!(f, "not a PNG or BMP image"),
+            ImageError::Unsupported(feature) => write!(f, "unsupported image: {feature}"),
+            ImageError::Truncated => write!(f, "image is truncated"),
+            ImageError::Invalid(field) => write!(f, "invalid {field} in image"),
+            ImageError::NoCells => write!(f, "no cells found in image"),
+        }
+    }
+}
+
+/// How cells are found in an image. Anything left `None` is detected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImageImportOptions {
+    /// Distance in pixels between adjacent cells, including any grid line
+    pub cell_size: Option<u32>,
+    /// Top left pixel of the first cell as `(x, y)`
+    pub offset: Option<(u32, u32)>,
+    /// Gray level splitting dark from light pixels
+    pub threshold: Option<u8>,
+    /// Whether alive cells are darker than dead ones, rather than whichever
+    /// is rarer
+    pub alive_dark: Option<bool>,
+}
+
+/// 8-bit grayscale image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrayImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Image import methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Replaces the universe with the cells found in a PNG or BMP image,
+    /// detecting the cell size and offset unless given.
+    #[cfg(feature = "wasm")]
+    pub fn reset_from_image(
+        &mut self,
+        image: &[u8],
+        cell_size: Option<u32>,
+        offset_x: Option<u32>,
+        offset_y: Option<u32>,
+    ) -> Result<(), JsValue> {
+        let options = ImageImportOptions {
+            cell_size,
+            offset: offset_x.zip(offset_y),
+            ..Default::default()
+        };
+        let universe =
+            Self::of_image(image, &options).map_err(|e| TypeError::new(e.to_string().as_ref()))?;
+        self.replace_with(universe);
+        Ok(())
+    }
+}
+
+impl Universe {
+    /// Creates a universe from the cells found in a PNG or BMP image.
+    pub fn of_image(image: &[u8], options: &ImageImportOptions) -> Result<Self, Box<dyn Error>> {
+        Self::of_grid(Grid::from_image(image, options)?)
+    }
+}
+
+impl Grid {
+    /// Reads the cells in a PNG or BMP image.
+    pub fn from_image(image: &[u8], options: &ImageImportOptions) -> Result<Grid, ImageError> {
+        Self::from_gray_image(&decode_image(image)?, options)
+    }
+
+    /// Reads the cells in a grayscale image.
+    pub fn from_gray_image(
+        image: &GrayImage,
+        options: &ImageImportOptions,
+    ) -> Result<Grid, ImageError> {
+        let (width, height) = (image.width, image.height);
+        if Some(image.pixels.len()) != (width as usize).checked_mul(height as usize) {
+            return Err(ImageError::Invalid("pixel count"));
+        }
+        let threshold = options
+            .threshold
+            .unwrap_or_else(|| otsu_threshold(&image.pixels));
+        let dark = image.pixels.iter().filter(|&&p| p <= threshold).count();
+        let alive_dark = options.alive_dark.unwrap_or(dark * 2 <= image.pixels.len());
+        let alive: Vec<bool> = image
+            .pixels
+            .iter()
+            .map(|&p| (p <= threshold) == alive_dark)
+            .collect();
+
+        let at = |x: u32, y: u32| alive[y as usize * width as usize + x as usize];
+        let runs: Runs = [
+            runs(width, height, at),
+            runs(height, width, |y, x| at(x, y)),
+        ];
+        let (pitch, lines) = match options.cell_size {
+            Some(0) => return Err(ImageError::Invalid("cell size")),
+            Some(pitch) => (pitch, best_lines(pitch, &runs)),
+            None => detect_pitch(&runs).ok_or(ImageError::NoCells)?,
+        };
+        let offset = match options.offset {
+            Some(offset) => offset,
+            None => (phase(pitch, &runs[0]), phase(pitch, &runs[1])),
+        };
+
+        // sample the middle of each cell
+        let size = pitch - lines;
+        let (start, end) = (size / 4, (size - size / 4).max(size / 4 + 1));
+        // cells all end within the image, so their pixels can't overflow
+        let count = |offset: u32, len: u32| match offset
+            .checked_add(size)
+            .and_then(|end| len.checked_sub(end))
+        {
+            Some(rest) => rest / pitch + 1,
+            None => 0,
+        };
+        let (cols, rows) = (count(offset.0, width), count(offset.1, height));
+        if cols == 0 || rows == 0 {
+            return Err(ImageError::NoCells);
+        }
+        let mut grid = Grid::new(cols as usize, rows as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                let (x, y) = (offset.0 + col * pitch, offset.1 + row * pitch);
+                let (mut on, mut total) = (0, 0);
+                for y in y + start..y + end {
+                    for x in x + start..x + end {
+                        on += at(x, y) as u32;
+                        total += 1;
+                    }
+                }
+                if on * 2 > total {
+                    grid[(col as usize, row as usize)] = Cell::Alive;
+                }
+            }
+        }
+        Ok(grid)
+    }
+}
+
+/// Run of same colored pixels along a row or column, not touching the edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Run {
+    alive: bool,
+    start: u32,
+    len: u32,
+}
+
+impl Run {
+    /// Whether the run spans whole cells of `pitch` pixels, with `lines`
+    /// pixel grid lines between them.
+    fn fits(&self, pitch: u32, lines: u32) -> bool {
+        let len = if self.alive {
+            self.len + lines
+        } else {
+            self.len - lines
+        };
+        len % pitch == 0
+    }
+}
+
+/// Runs along each of `lines` lines `len` pixels long.
+fn runs(len: u32, lines: u32, alive: impl Fn(u32, u32) -> bool) -> Vec<Run> {
+    let mut runs = Vec::new();
+    for line in 0..lines {
+        let mut start = 0;
+        for i in 1..=len {
+            if i < len && alive(i, line) == alive(start, line) {
+                continue;
+            }
+            if start > 0 && i < len {
+                runs.push(Run {
+                    alive: alive(start, line),
+                    start,
+                    len: i - start,
+                });
+            }
+            start = i;
+        }
+    }
+    runs
+}
+
+/// Runs along rows and along columns.
+type Runs = [Vec<Run>; 2];
+
+/// Fraction of runs fitting a pitch and grid line width, and starting where
+/// cells or grid lines would.
+fn pitch_score(pitch: u32, lines: u32, runs: &Runs) -> f64 {
+    let (mut fit, mut total) = (0, 0);
+    for axis in runs {
+        let phase = phase(pitch, axis);
+        for run in axis {
+            let start = if run.alive {
+                run.start
+            } else {
+                run.start + lines
+            };
+            fit += (run.fits(pitch, lines) && start % pitch == phase) as u32;
+            total += 1;
+        }
+    }
+    fit as f64 / total.max(1) as f64
+}
+
+/// Grid line width best fitting a pitch.
+fn best_lines(pitch: u32, runs: &Runs) -> u32 {
+    let with_lines = pitch > 1 && pitch_score(pitch, 1, runs) > pitch_score(pitch, 0, runs);
+    with_lines as u32
+}
+
+/// The largest pitch, and its grid line width, that most runs fit.
+fn detect_pitch(runs: &Runs) -> Option<(u32, u32)> {
+    // cells are no larger than the shortest alive run, plus a grid line
+    let shortest = runs
+        .iter()
+        .flatten()
+        .filter(|run| run.alive)
+        .map(|run| run.len)
+        .min()?;
+    (1..=shortest + 1).rev().find_map(|pitch| {
+        let lines = best_lines(pitch, runs);
+        (pitch_score(pitch, lines, runs) >= MIN_PITCH_SCORE).then_some((pitch, lines))
+    })
+}
+
+/// Most common start of alive runs, modulo the pitch.
+fn phase(pitch: u32, runs: &[Run]) -> u32 {
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    for run in runs.iter().filter(|run| run.alive) {
+        *counts.entry(run.start % pitch).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(phase, count)| (count, std::cmp::Reverse(phase)))
+        .map_or(0, |(phase, _)| phase)
+}
+
+/// Threshold maximizing the variance between dark and light pixels.
+fn otsu_threshold(pixels: &[u8]) -> u8 {
+    let mut histogram = [0u64; 256];
+    for &p in pixels {
+        histogram[p as usize] += 1;
+    }
+    let total = pixels.len() as f64;
+    let sum: f64 = (0..256).map(|i| i as f64 * histogram[i] as f64).sum();
+
+    let (mut best, mut best_variance) = (127, -1.0);
+    let (mut dark, mut dark_sum) = (0.0, 0.0);
+    for (i, &count) in histogram.iter().enumerate() {
+        dark += count as f64;
+        dark_sum += i as f64 * count as f64;
+        let light = total - dark;
+        if dark == 0.0 || light == 0.0 {
+            continue;
+        }
+        let mean_difference = dark_sum / dark - (sum - dark_sum) / light;
+        let variance = dark * light * mean_difference * mean_difference;
+        if variance > best_variance {
+            (best, best_variance) = (i as u8, variance);
+        }
+    }
+    best
+}
+
+/// Decodes a PNG or BMP image to grayscale, blending any transparency over
+/// white.
+pub fn decode_image(bytes: &[u8]) -> Result<GrayImage, ImageError> {
+    if bytes.starts_with(&PNG_SIGNATURE) {
+        decode_png(&bytes[PNG_SIGNATURE.len()..])
+    } else if bytes.starts_with(b"BM") {
+        decode_bmp(bytes)
+    } else {
+        Err(ImageError::UnknownFormat)
+    }
+}
+
+fn luma([r, g, b, a]: [u8; 4]) -> u8 {
+    let gray = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+    ((gray * a as u32 + 0xFF * (0xFF - a as u32)) / 0xFF) as u8
+}
+
+fn check_size(width: u32, height: u32) -> Result<usize, ImageError> {
+    let pixels = width as u64 * height as u64;
+    if pixels > MAX_PIXELS {
+        return Err(ImageError::Unsupported("too many pixels"));
+    }
+    Ok(pixels as usize)
+}
+
+/// Bits in a row of `width` pixels, failing if they don't fit in a `usize`.
+fn row_bits(width: u32, bits_per_pixel: usize) -> Result<usize, ImageError> {
+    (width as usize)
+        .checked_mul(bits_per_pixel)
+        .ok_or(ImageError::Unsupported("too many pixels"))
+}
+
+fn decode_png(mut chunks: &[u8]) -> Result<GrayImage, ImageError> {
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut data = Vec::new();
+    loop {
+        if chunks.len() < 12 {
+            return Err(ImageError::Truncated);
+        }
+        let len = u32::from_be_bytes(chunks[..4].try_into().unwrap()) as usize;
+        let kind = &chunks[4..8];
+        // lengths near u32::MAX overflow a 32-bit usize
+        let body = chunks[8..].get(..len).ok_or(ImageError::Truncated)?;
+        chunks = len
+            .checked_add(12)
+            .and_then(|end| chunks.get(end..))
+            .ok_or(ImageError::Truncated)?;
+        match kind {
+            b"IHDR" if body.len() == 13 => header = Some(body),
+            b"IHDR" => return Err(ImageError::Invalid("header")),
+            b"PLTE" => palette = body,
+            b"IDAT" => data.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or(ImageError::Invalid("header"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if interlace != 0 {
+        return Err(ImageError::Unsupported("interlaced PNG"));
+    }
+    let channels = match (color_type, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) | (3, 1 | 2 | 4 | 8) => 1,
+        (2 | 6, 8 | 16) => 3 + (color_type == 6) as usize,
+        (4, 8 | 16) => 2,
+        _ => return Err(ImageError::Invalid("color type or bit depth")),
+    };
+    let pixels = check_size(width, height)?;
+
+    let bits = channels * depth as usize;
+    let stride = row_bits(width, bits)?.div_ceil(8);
+    let len = (stride + 1)
+        .checked_mul(height as usize)
+        .ok_or(ImageError::Unsupported("too many pixels"))?;
+    let mut raw = decompress_to_vec_zlib_with_limit(&data, len)
+        .map_err(|_| ImageError::Invalid("compressed data"))?;
+    if raw.len() < len {
+        return Err(ImageError::Truncated);
+    }
+    unfilter(&mut raw, stride, bits.div_ceil(8))?;
+
+    let max = (1u32 << depth.min(8)) - 1;
+    let mut out = Vec::with_capacity(pixels);
+    for row in raw.chunks_exact(stride + 1).take(height as usize) {
+        let row = &row[1..];
+        // samples as bytes, taking the high byte of 16-bit ones
+        let sample = |i: usize| -> u8 {
+            match depth {
+                8 => row[i],
+                16 => row[i * 2],
+                _ => {
+                    let bit = i * depth as usize;
+                    let value = (row[bit / 8] >> (8 - depth as usize - bit % 8)) as u32 & max;
+                    if color_type == 3 {
+                        value as u8
+                    } else {
+                        (value * 0xFF / max) as u8
+                    }
+                }
+            }
+        };
+        for x in 0..width as usize {
+            let i = x * channels;
+            let color = match color_type {
+                0 => [sample(i), sample(i), sample(i), 0xFF],
+                2 => [sample(i), sample(i + 1), sample(i + 2), 0xFF],
+                3 => {
+                    let entry = sample(i) as usize * 3;
+                    let rgb = palette
+                        .get(entry..entry + 3)
+                        .ok_or(ImageError::Invalid("palette index"))?;
+                    [rgb[0], rgb[1], rgb[2], 0xFF]
+                }
+                4 => [sample(i), sample(i), sample(i), sample(i + 1)],
+                _ => [sample(i), sample(i + 1), sample(i + 2), sample(i + 3)],
+            };
+            out.push(luma(color));
+        }
+    }
+    Ok(GrayImage {
+        width,
+        height,
+        pixels: out,
+    })
+}
+
+/// Reverses the filter each PNG row starts with, in place.
+fn unfilter(raw: &mut [u8], stride: usize, bpp: usize) -> Result<(), ImageError> {
+    let mut above = vec![0; stride];
+    for row in raw.chunks_exact_mut(stride + 1) {
+        let (filter, row) = row.split_first_mut().unwrap();
+        for i in 0..stride {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            let up_left = if i >= bpp { above[i - bpp] } else { 0 };
+            let up = above[i];
+            let prediction = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(ImageError::Invalid("filter")),
+            };
+            row[i] = row[i].wrapping_add(prediction);
+        }
+        above.copy_from_slice(row);
+    }
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn decode_bmp(bytes: &[u8]) -> Result<GrayImage, ImageError> {
+    let u16_at = |i: usize| {
+        bytes
+            .get(i..i + 2)
+            .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+            .ok_or(ImageError::Truncated)
+    };
+    let u32_at = |i: usize| {
+        bytes
+            .get(i..i + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or(ImageError::Truncated)
+    };
+    let data_start = u32_at(10)? as usize;
+    let header_len = u32_at(14)? as usize;
+    let width = u32_at(18)? as i32;
+    let height = u32_at(22)? as i32;
+    let bits = u16_at(28)? as usize;
+    let compression = u32_at(30)?;
+    // bitfields are accepted assuming the usual BGRA layout
+    if compression != 0 && !(compression == 3 && bits == 32) {
+        return Err(ImageError::Unsupported("compressed BMP"));
+    }
+    if width < 0 || height == i32::MIN {
+        return Err(ImageError::Invalid("dimensions"));
+    }
+    let (width, top_down) = (width as u32, height < 0);
+    let height = height.unsigned_abs();
+    let pixels = check_size(width, height)?;
+
+    let palette = match bits {
+        1 | 4 | 8 => {
+            let colors = match u32_at(46)? {
+                0 => 1 << bits,
+                n => n as usize,
+            };
+            header_len
+                .checked_add(14)
+                .and_then(|start| bytes.get(start..))
+                .and_then(|rest| rest.get(..colors.checked_mul(4)?))
+                .ok_or(ImageError::Truncated)?
+        }
+        24 | 32 => &[],
+        _ => return Err(ImageError::Unsupported("BMP bit depth")),
+    };
+
+    let stride = row_bits(width, bits)?.div_ceil(32) * 4;
+    let mut out = Vec::with_capacity(pixels);
+    for y in 0..height as usize {
+        let row = if top_down { y } else { height as usize - 1 - y };
+        let row = row
+            .checked_mul(stride)
+            .and_then(|offset| offset.checked_add(data_start))
+            .and_then(|start| bytes.get(start..))
+            .and_then(|rest| rest.get(..stride))
+            .ok_or(ImageError::Truncated)?;
+        for x in 0..width as usize {
+            let color = match bits {
+                24 | 32 => {
+                    let i = x * bits / 8;
+                    [row[i + 2], row[i + 1], row[i], 0xFF]
+                }
+                _ => {
+                    let bit = x * bits;
+                    let index = (row[bit / 8] >> (8 - bits - bit % 8)) as usize & ((1 << bits) - 1);
+                    let bgr = palette
+                        .get(index * 4..index * 4 + 3)
+                        .ok_or(ImageError::Invalid("palette index"))?;
+                    [bgr[2], bgr[1], bgr[0], 0xFF]
+                }
+            };
+            out.push(luma(color));
+        }
+    }
+    Ok(GrayImage {
+        width,
+        height,
+        pixels: out,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{encode_png, Framebuffer, RenderOptions};
+
+    fn glider() -> Grid {
+        crate::parse_plaintext("!\n......\n..O...\n...O..\n.OOO..\n......\n").unwrap()
+    }
+
+    fn render(grid: &Grid, cell_size: u32, grid_lines: bool) -> Vec<u8> {
+        let options = RenderOptions {
+            cell_size,
+            grid_lines,
+            ..Default::default()
+        };
+        let framebuffer = Framebuffer::of_grid(grid, options);
+        encode_png(
+            framebuffer.width(),
+            framebuffer.height(),
+            framebuffer.as_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for cell_size in [1, 3, 8] {
+            for grid_lines in [false, true] {
+                let png = render(&glider(), cell_size, grid_lines);
+                let grid = Grid::from_image(&png, &Default::default()).unwrap();
+                assert_eq!(glider(), grid, "{cell_size} {grid_lines}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_light_on_dark_with_margin() {
+        // light cells 5 pixels apart, starting 3 pixels in
+        let mut pixels = vec![0x20; 40 * 30];
+        for (row, col) in [(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)] {
+            for y in 0..4 {
+                for x in 0..4 {
+                    pixels[(3 + row * 5 + y) * 40 + 3 + col * 5 + x] = 0xE0;
+                }
+            }
+        }
+        let image = GrayImage {
+            width: 40,
+            height: 30,
+            pixels,
+        };
+        let grid = Grid::from_gray_image(&image, &Default::default()).unwrap();
+        assert_eq!((7, 5), (grid.width, grid.height));
+        assert_eq!(Cell::Alive, grid[(2, 1)]);
+        assert_eq!(5, grid.cells.iter().filter(|&&c| c == Cell::Alive).count());
+
+        // a block is ambiguous, so give the cell size and offset
+        let block = crate::parse_plaintext("!\n....\n.OO.\n.OO.\n....\n").unwrap();
+        let png = render(&block, 4, false);
+        let options = ImageImportOptions {
+            cell_size: Some(4),
+            offset: Some((0, 0)),
+            ..Default::default()
+        };
+        assert_eq!(block, Grid::from_image(&png, &options).unwrap());
+    }
+
+    #[test]
+    fn test_png_formats() {
+        // 1-bit grayscale and indexed PNGs of 2 pixel cells
+        let cells = [[0, 1, 0], [0, 0, 1], [1, 1, 1]];
+        let mut pixels = vec![1u8; 10 * 10];
+        for (row, line) in cells.iter().enumerate() {
+            for (col, &alive) in line.iter().enumerate() {
+                for (y, x) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                    pixels[(2 + row * 2 + y) * 10 + 2 + col * 2 + x] = 1 - alive;
+                }
+            }
+        }
+        // the margin on the right is as wide as a cell
+        let expected = crate::parse_plaintext("!\n.O..\n..O.\nOOO.\n....\n").unwrap();
+        let options = ImageImportOptions {
+            offset: Some((2, 2)),
+            ..Default::default()
+        };
+
+        let mut packed = Vec::new();
+        for row in pixels.chunks(10) {
+            let bits = row.iter().fold(0u16, |bits, &p| bits << 1 | p as u16) << 6;
+            packed.extend_from_slice(&bits.to_be_bytes());
+        }
+        for (color, depth, palette, data) in [
+            (png::ColorType::Grayscale, png::BitDepth::One, None, packed),
+            (
+                png::ColorType::Indexed,
+                png::BitDepth::Eight,
+                Some(vec![0x10, 0x40, 0x10, 0xF0, 0xF0, 0xF0]),
+                pixels.clone(),
+            ),
+        ] {
+            let mut bytes = Vec::new();
+            let mut encoder = png::Encoder::new(&mut bytes, 10, 10);
+            encoder.set_color(color);
+            encoder.set_depth(depth);
+            if let Some(palette) = palette {
+                encoder.set_palette(palette);
+            }
+            encoder
+                .write_header()
+                .unwrap()
+                .write_image_data(&data)
+                .unwrap();
+            let grid = Grid::from_image(&bytes, &options).unwrap();
+            assert_eq!(expected, grid, "{color:?}");
+        }
+    }
+
+    #[test]
+    fn test_bmp() {
+        // 24-bit, bottom up, 2 by 2 pixels with rows padded to 8 bytes
+        let mut bmp = b"BM".to_vec();
+        bmp.extend_from_slice(&(54u32 + 16).to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&54u32.to_le_bytes());
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        bmp.extend_from_slice(&2i32.to_le_bytes());
+        bmp.extend_from_slice(&2i32.to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&24u16.to_le_bytes());
+        bmp.extend_from_slice(&[0; 24]);
+        // bottom row: white, black; top row: black, white
+        bmp.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0, 0, 0, 0, 0]);
+        bmp.extend_from_slice(&[0, 0, 0, 0xFF, 0xFF, 0xFF, 0, 0]);
+
+        let image = decode_image(&bmp).unwrap();
+        assert_eq!(vec![0, 0xFF, 0xFF, 0], image.pixels);
+        assert_eq!(
+            Err(ImageError::Truncated),
+            decode_image(&bmp[..bmp.len() - 1])
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let options = ImageImportOptions::default();
+        assert_eq!(
+            Some(ImageError::UnknownFormat),
+            Grid::from_image(b"x = 3, y = 3", &options).err()
+        );
+        // nothing to measure cells by
+        let blank = render(&Grid::new(4, 4), 4, false);
+        assert_eq!(
+            Some(ImageError::NoCells),
+            Grid::from_image(&blank, &options).err()
+        );
+        let png = render(&glider(), 4, false);
+        assert_eq!(
+            Some(ImageError::Truncated),
+            Grid::from_image(&png[..png.len() - 20], &options).err()
+        );
+        // a chunk length of u32::MAX
+        let mut long_chunk = png[..8].to_vec();
+        long_chunk.extend_from_slice(&[0xFF; 4]);
+        long_chunk.extend_from_slice(&png[12..]);
+        assert_eq!(
+            Some(ImageError::Truncated),
+            Grid::from_image(&long_chunk, &options).err()
+        );
+
+        for (cell_size, offset) in [
+            (Some(4), Some((u32::MAX, 0))),
+            (Some(4), Some((0, u32::MAX - 1))),
+            (Some(u32::MAX), None),
+        ] {
+            let options = ImageImportOptions {
+                cell_size,
+                offset,
+                ..Default::default()
+            };
+            assert_eq!(
+                Some(ImageError::NoCells),
+                Grid::from_image(&png, &options).err(),
+                "{options:?}"
+            );
+        }
+
+        let image = GrayImage {
+            width: 4,
+            height: 4,
+            pixels: vec![0; 15],
+        };
+        assert_eq!(
+            Some(ImageError::Invalid("pixel count")),
+            Grid::from_gray_image(&image, &options).err()
+        );
+    }
+}
@@ -2,8 +2,44 @@ use log::trace;
 
 mod utils;
 use utils::Timer;
+#[macro_use]
 mod parse;
 pub use parse::*;
+mod history;
+use history::{unpack_cells, EntryKind, History, Route};
+mod transform;
+pub use transform::*;
+mod paste;
+pub use paste::*;
+mod random;
+pub use random::*;
+mod render;
+pub use render::*;
+mod activity;
+use activity::Activity;
+pub use activity::MAX_HEAT;
+mod overlay;
+use overlay::Overlay;
+pub use overlay::{
+    from_life_history, to_life_history, LIFE_HISTORY_STATES, OVERLAY_BOUNDARY, OVERLAY_HISTORY,
+    OVERLAY_MARK,
+};
+mod selection;
+pub use selection::*;
+mod draw;
+pub use draw::*;
+mod snapshot;
+pub use snapshot::*;
+mod share;
+pub use share::*;
+mod period;
+pub use period::*;
+mod image;
+pub use image::*;
+mod catalogue;
+pub use catalogue::*;
+#[cfg(feature = "serde")]
+mod serialize;
 
 #[cfg(feature = "wasm")]
 use js_sys::TypeError;
@@ -16,9 +52,17 @@ use wasm_bindgen::{prelude::*, JsValue};
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+/// Rule universes evolve by, in B/S notation.
+pub const RULE: &str = "B3/S23";
+
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Cell {
     Dead = 0,
     Alive = 1,
@@ -34,6 +78,11 @@ impl Cell {
 }
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EdgeBehavior {
     Wrap,
@@ -42,6 +91,41 @@ pub enum EdgeBehavior {
     // Grow,
 }
 
+/// Point of the universe that stays in place when resizing.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Offset of the old contents within the new bounds, as `(rows, cols)`.
+    fn offset(self, old: (u32, u32), new: (u32, u32)) -> (i64, i64) {
+        let (old_height, old_width) = (old.0 as i64, old.1 as i64);
+        let (new_height, new_width) = (new.0 as i64, new.1 as i64);
+        let (d_height, d_width) = (new_height - old_height, new_width - old_width);
+        let row = match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => 0,
+            Anchor::Left | Anchor::Center | Anchor::Right => d_height / 2,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => d_height,
+        };
+        let col = match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => d_width / 2,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => d_width,
+        };
+        (row, col)
+    }
+}
+
 // TODO: use fixedbitset for storing cells
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Universe {
@@ -52,6 +136,12 @@ pub struct Universe {
     delta_alive: Vec<u32>,
     delta_dead: Vec<u32>,
     edge_behavior: EdgeBehavior,
+    generation: u32,
+    history: History,
+    activity: Option<Activity>,
+    overlay: Option<Overlay>,
+    selection: Option<Selection>,
+    clipboard: Option<Grid>,
 }
 
 #[cfg(feature = "wasm")]
@@ -67,7 +157,6 @@ pub fn wasm_main() {
 /// public methods for JS
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Universe {
-    // TODO: random generation of initial layout with js-sys
     pub fn new(width: u32, height: u32) -> Universe {
         let cells = vec![Cell::Dead; (width * height) as usize];
         Self::of_cells(width, height, cells)
@@ -75,45 +164,46 @@ impl Universe {
 
     #[cfg(feature = "wasm")]
     pub fn reset_from_file(&mut self, f: &[u8]) -> Result<(), JsValue> {
-        *self = Self::of_file(f).map_err(|e| TypeError::new(e.to_string().as_ref()))?;
+        let universe = Self::of_file(f).map_err(|e| TypeError::new(e.to_string().as_ref()))?;
+        self.replace_with(universe);
         Ok(())
     }
 
     pub fn reset_blank(&mut self) {
-        for i in 0..(self.height * self.width) as usize {
-            self.cells[i] = Cell::Dead;
-        }
+        self.reset_with(|_i| Cell::Dead);
     }
 
     pub fn reset_fancy(&mut self) {
-        for i in 0..(self.height * self.width) as usize {
-            self.cells[i] = if i % 2 == 0 || i % 7 == 0 {
-                Cell::Alive
-            } else {
-                Cell::Dead
-            };
-        }
-    }
-
-    #[cfg(feature = "wasm")]
-    pub fn reset_random(&mut self) {
-        for i in 0..(self.height * self.width) as usize {
-            self.cells[i] = if js_sys::Math::random() < 0.5 {
+        self.reset_with(|i| {
+            if i % 2 == 0 || i % 7 == 0 {
                 Cell::Alive
             } else {
                 Cell::Dead
-            };
-        }
+            }
+        });
     }
 
     /// Returns a Unicode grid in a string, representing the Universe.
+    ///
+    /// See [`render_text`](Self::render_text) for denser styles and colors.
     pub fn render(&self) -> String {
         self.to_string()
     }
 
+    /// Returns the universe as RLE, using LifeHistory states if the overlay
+    /// is enabled.
+    pub fn to_rle(&self) -> String {
+        let grid = self.region(0, 0, self.width, self.height);
+        match self.get_overlay() {
+            Some(overlay) => write_rle_with_overlay(&grid, overlay),
+            None => write_rle(&grid),
+        }
+    }
+
     /// Updates the Universe, bringing cells into and out of existence.
     pub fn tick(&mut self) {
         let _timer = Timer::new("Universe::tick");
+        let mut flipped = Vec::new();
         {
             let _timer = Timer::new("new generation");
             for row in 0..self.height {
@@ -123,9 +213,16 @@ impl Universe {
                     let live_neighbors = self.live_neighbor_count(row, col);
 
                     trace!("cell[{row}, {col}] is initially {cell:?} and has {live_neighbors} live neighbors");
-                    let next_cell = Self::update_status(cell, live_neighbors);
+                    let next_cell = if self.is_boundary(idx) {
+                        Cell::Dead
+                    } else {
+                        Self::update_status(cell, live_neighbors)
+                    };
                     trace!("it becomes {next_cell:?}");
 
+                    if cell != next_cell && self.history.is_enabled() {
+                        flipped.push(idx as u32);
+                    }
                     self.old_cells[idx] = next_cell;
                 }
             }
@@ -133,6 +230,10 @@ impl Universe {
 
         let _timer2 = Timer::new("swap cell buffers");
         mem::swap(&mut self.old_cells, &mut self.cells);
+        if let Some(activity) = &mut self.activity {
+            activity.advance(&self.old_cells, &self.cells);
+        }
+        self.record_tick(flipped);
     }
 
     /// Updates the Universe, bringing cells into and out of existence.
@@ -140,6 +241,7 @@ impl Universe {
         let _timer = Timer::new("Universe::tick_delta");
         self.delta_alive.clear();
         self.delta_dead.clear();
+        let mut flipped = Vec::new();
         {
             let _timer = Timer::new("new generation");
             for row in 0..self.height {
@@ -149,17 +251,28 @@ impl Universe {
                     let live_neighbors = self.live_neighbor_count(row, col);
 
                     trace!("cell[{row}, {col}] is initially {cell:?} and has {live_neighbors} live neighbors");
-                    let next_cell = Self::update_status(cell, live_neighbors);
+                    let next_cell = if self.is_boundary(idx) {
+                        Cell::Dead
+                    } else {
+                        Self::update_status(cell, live_neighbors)
+                    };
                     trace!("it becomes {next_cell:?}");
 
                     if cell != next_cell {
                         self.buffer_delta(row, col, next_cell);
+                        if self.history.is_enabled() {
+                            flipped.push(idx as u32);
+                        }
                     }
                     self.old_cells[idx] = next_cell;
                 }
             }
         }
         mem::swap(&mut self.old_cells, &mut self.cells);
+        if let Some(activity) = &mut self.activity {
+            activity.advance(&self.old_cells, &self.cells);
+        }
+        self.record_tick(flipped);
     }
 
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
@@ -175,7 +288,7 @@ impl Universe {
     #[cfg_attr(feature = "wasm", wasm_bindgen(setter))]
     /// Set the width of the universe.
     ///
-    /// Resets all cells to the dead state.
+    /// Resets all cells to the dead state and clears the history.
     pub fn set_width(&mut self, width: u32) {
         self.width = width;
         self.make_cells();
@@ -184,12 +297,45 @@ impl Universe {
     #[cfg_attr(feature = "wasm", wasm_bindgen(setter))]
     /// Set the height of the universe.
     ///
-    /// Resets all cells to the dead state.
+    /// Resets all cells to the dead state and clears the history.
     pub fn set_height(&mut self, height: u32) {
         self.height = height;
         self.make_cells();
     }
 
+    /// Changes the dimensions of the universe, keeping the existing cells.
+    ///
+    /// The `anchor` point of the old cells stays at the same point of the
+    /// resized universe; cells are cropped or dead cells padded around it as
+    /// needed.
+    ///
+    /// Clears the delta buffers, the history and the selection, as none match
+    /// the new dimensions. The overlay is moved along with the cells.
+    pub fn resize(&mut self, width: u32, height: u32, anchor: Anchor) {
+        let offset = anchor.offset((self.height, self.width), (height, width));
+        self.resize_with_offset(width, height, offset);
+    }
+
+    /// Changes the dimensions of the universe, moving each cell by `offset`
+    /// as `(rows, cols)`.
+    fn resize_with_offset(&mut self, width: u32, height: u32, offset: (i64, i64)) {
+        let cells = self.resized(&self.cells, Cell::Dead, (width, height), offset);
+        if let Some(overlay) = &self.overlay {
+            let flags = self.resized(&overlay.flags, 0, (width, height), offset);
+            self.overlay = Some(Overlay { flags });
+        }
+
+        self.width = width;
+        self.height = height;
+        self.old_cells = cells.clone();
+        self.cells = cells;
+        self.delta_alive.clear();
+        self.delta_dead.clear();
+        self.history.clear();
+        self.set_track_activity(self.track_activity());
+        self.selection = None;
+    }
+
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
     pub fn edge_behavior(&self) -> EdgeBehavior {
         self.edge_behavior
@@ -229,6 +375,121 @@ impl Universe {
         let idx = self.get_index(row, column);
         self.cells[idx].toggle();
         self.buffer_delta(row, column, self.cells[idx]);
+        self.record_edit(vec![idx as u32]);
+    }
+
+    /// Number of generations the universe has been advanced.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Number of alive cells.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn population(&self) -> u32 {
+        self.cells.iter().filter(|&&c| c == Cell::Alive).count() as u32
+    }
+
+    /// Maximum number of bytes used to store the history.
+    ///
+    /// Once exceeded, the oldest changes are forgotten.
+    /// The default of 0 disables recording.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn history_budget(&self) -> usize {
+        self.history.budget()
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(setter))]
+    pub fn set_history_budget(&mut self, bytes: usize) {
+        self.history.set_budget(bytes);
+    }
+
+    /// Number of changes stored in the history.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Position in the history, between 0 and [`history_len`](Self::history_len).
+    ///
+    /// Changes before the position have been applied, changes after it have
+    /// been undone.
+    pub fn history_position(&self) -> usize {
+        self.history.position()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Reverts the last edit or tick.
+    ///
+    /// Changed cells are written to the delta buffers.
+    /// Returns `false` if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.position() {
+            0 => false,
+            p => self.seek_history(p - 1),
+        }
+    }
+
+    /// Reapplies the last undone edit or tick.
+    ///
+    /// Changed cells are written to the delta buffers.
+    /// Returns `false` if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.seek_history(self.history.position() + 1)
+    }
+
+    /// Returns to the previous generation, undoing any edits made since.
+    ///
+    /// Changed cells are written to the delta buffers.
+    /// Returns `false` if the previous generation is not in the history.
+    pub fn step_back(&mut self) -> bool {
+        let last_tick = (0..self.history.position())
+            .rev()
+            .find(|&i| self.history.kind(i) == Some(EntryKind::Tick));
+        match last_tick {
+            Some(i) => self.seek_history(i),
+            None => false,
+        }
+    }
+
+    /// Moves to a position in the history, undoing or redoing changes.
+    ///
+    /// Changed cells are written to the delta buffers.
+    /// Returns `false` if the position is outside of the history.
+    pub fn seek_history(&mut self, position: usize) -> bool {
+        if position > self.history.len() {
+            return false;
+        }
+        let Some(generation) = self.history.generation_at(position) else {
+            return false;
+        };
+        self.delta_alive.clear();
+        self.delta_dead.clear();
+
+        // take the history to apply its entries while mutating the cells
+        let mut history = mem::take(&mut self.history);
+        if let Route::Keyframe(packed) = history.route_to(position) {
+            let keyframe: Vec<_> = unpack_cells(packed, self.cells.len()).collect();
+            for (idx, cell) in keyframe.into_iter().enumerate() {
+                if self.cells[idx] != cell {
+                    self.set_cell_delta(idx, cell);
+                }
+            }
+        }
+        while history.position() > position {
+            let entry = history.undo().unwrap();
+            self.flip_cells(&entry.flipped);
+        }
+        while history.position() < position {
+            let entry = history.redo().unwrap();
+            self.flip_cells(&entry.flipped);
+        }
+        self.history = history;
+
+        self.generation = generation;
+        true
     }
 }
 
@@ -246,9 +507,26 @@ impl Universe {
             delta_alive: Vec::new(),
             delta_dead: Vec::new(),
             edge_behavior: EdgeBehavior::Wrap,
+            generation: 0,
+            history: History::default(),
+            activity: None,
+            overlay: None,
+            selection: None,
+            clipboard: None,
         }
     }
 
+    /// Copies the cells, edge behavior, generation, overlay and activity, to
+    /// simulate ahead without touching this universe or its history.
+    pub(crate) fn copy_state(&self) -> Universe {
+        let mut universe = Universe::of_cells(self.width, self.height, self.cells.clone());
+        universe.edge_behavior = self.edge_behavior;
+        universe.generation = self.generation;
+        universe.activity = self.activity.clone();
+        universe.overlay = self.overlay.clone();
+        universe
+    }
+
     fn of_grid(
         Grid {
             width,
@@ -259,10 +537,57 @@ impl Universe {
         Ok(Self::of_cells(width.try_into()?, height.try_into()?, cells))
     }
 
+    /// Creates a universe from a pattern file.
+    ///
+    /// Enables the overlay if the file's rule is LifeHistory.
     pub fn of_file(f: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Self::of_pattern(parse_str_with_overlay(std::str::from_utf8(f)?)?)
+    }
+
+    /// Creates a universe from an RLE file, optionally read leniently.
+    pub fn of_rle(input: &str, options: &RleOptions) -> Result<Self, Box<dyn Error>> {
+        Self::of_pattern(parse_rle_with_options(input, options)?)
+    }
+
+    /// Creates a universe from a pattern with a state per cell.
+    ///
+    /// Fails with [`MultiStateError`] unless the states are two-state, or
+    /// LifeHistory ones under that rule, which enables the overlay.
+    pub fn of_state_grid(grid: &StateGrid) -> Result<Self, Box<dyn Error>> {
+        Self::of_pattern(grid.to_grid()?)
+    }
+
+    /// Creates a universe from a parsed pattern and its overlay, if any.
+    fn of_pattern((grid, overlay): (Grid, Option<Vec<u8>>)) -> Result<Self, Box<dyn Error>> {
+        let mut universe = Self::of_grid(grid)?;
+        if let Some(flags) = overlay {
+            universe.set_overlay(flags);
+        }
+        Ok(universe)
+    }
+
+    /// Replaces the universe with a loaded one, keeping the history budget,
+    /// activity tracking, overlay being enabled and clipboard.
+    #[cfg(feature = "wasm")]
+    fn replace_with(&mut self, universe: Universe) {
+        let budget = self.history.budget();
+        let track_activity = self.track_activity();
+        let overlay_enabled = self.overlay_enabled();
+        let clipboard = self.clipboard.take();
+        *self = universe;
+        self.clipboard = clipboard;
+        self.history.set_budget(budget);
+        self.set_track_activity(track_activity);
+        if overlay_enabled {
+            // keep the loaded overlay, if any
+            self.set_overlay_enabled(true);
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    fn grid_of_file(f: &[u8]) -> Result<Grid, Box<dyn Error>> {
         let f = std::str::from_utf8(f)?;
-        let grid = parse_str(f)?;
-        Self::of_grid(grid)
+        Ok(parse_str(f)?)
     }
 
     /// Get the dead and alive values of the entire universe.
@@ -273,10 +598,15 @@ impl Universe {
     /// Set cells to be alive in a universe by passing the row and column
     /// of each cell as an array.
     pub fn set_cells(&mut self, cells: &[(u32, u32)]) {
+        let mut flipped = Vec::new();
         for (row, col) in cells.iter().cloned() {
             let idx = self.get_index(row, col);
+            if self.cells[idx] == Cell::Dead {
+                flipped.push(idx as u32);
+            }
             self.cells[idx] = Cell::Alive;
         }
+        self.record_edit(flipped);
     }
 
     fn get_index(&self, row: u32, col: u32) -> usize {
@@ -287,18 +617,116 @@ impl Universe {
 
     fn get_rowcol(&self, index: usize) -> (u32, u32) {
         debug_assert!(index < self.cells.len());
-        let row = index / self.cells.len();
-        let col = index % self.cells.len();
+        let row = index / self.width as usize;
+        let col = index % self.width as usize;
         (row as u32, col as u32)
     }
 
     /// Sets the cells buffer to match height and width dimensions.
     ///
-    /// Resets all cells to the dead state.
+    /// Resets all cells to the dead state and clears the history and overlay.
     #[inline]
     fn make_cells(&mut self) {
         self.cells = (0..self.width * self.height).map(|_i| Cell::Dead).collect();
         self.old_cells = self.cells.clone();
+        self.history.clear();
+        self.set_track_activity(self.track_activity());
+        self.selection = None;
+        if self.overlay.is_some() {
+            self.overlay = Some(Overlay::new(&self.cells));
+        }
+    }
+
+    /// Copies per-cell `data` into a buffer of `width` by `height`, moving each
+    /// cell by `(d_row, d_col)` and filling uncovered cells with `fill`.
+    fn resized<T: Copy>(
+        &self,
+        data: &[T],
+        fill: T,
+        (width, height): (u32, u32),
+        (d_row, d_col): (i64, i64),
+    ) -> Vec<T> {
+        let mut resized = vec![fill; (width * height) as usize];
+        for row in 0..self.height {
+            let new_row = row as i64 + d_row;
+            if !(0..height as i64).contains(&new_row) {
+                continue;
+            }
+            for col in 0..self.width {
+                let new_col = col as i64 + d_col;
+                if !(0..width as i64).contains(&new_col) {
+                    continue;
+                }
+                resized[(new_row * width as i64 + new_col) as usize] =
+                    data[self.get_index(row, col)];
+            }
+        }
+        resized
+    }
+
+    /// Sets every cell from its index, recording the changes in the history.
+    fn reset_with(&mut self, mut f: impl FnMut(usize) -> Cell) {
+        let mut flipped = Vec::new();
+        for i in 0..self.cells.len() {
+            let cell = f(i);
+            if self.cells[i] != cell {
+                flipped.push(i as u32);
+            }
+            self.cells[i] = cell;
+        }
+        self.record_edit(flipped);
+    }
+
+    /// Sets cells by index as a single edit, buffering the changes.
+    ///
+    /// If an index appears more than once, the last state is used.
+    fn edit_cells(&mut self, changes: impl IntoIterator<Item = (usize, Cell)>) {
+        let changes: BTreeMap<_, _> = changes.into_iter().collect();
+        let mut flipped = Vec::new();
+        for (idx, cell) in changes {
+            if self.cells[idx] != cell {
+                self.set_cell_delta(idx, cell);
+                flipped.push(idx as u32);
+            }
+        }
+        self.record_edit(flipped);
+    }
+
+    fn record_edit(&mut self, flipped: Vec<u32>) {
+        if let Some(overlay) = &mut self.overlay {
+            for &idx in &flipped {
+                if self.cells[idx as usize] == Cell::Alive {
+                    overlay.flags[idx as usize] |= OVERLAY_HISTORY;
+                }
+            }
+        }
+        self.history
+            .record(EntryKind::Edit, flipped, self.generation, &self.cells);
+    }
+
+    fn record_tick(&mut self, flipped: Vec<u32>) {
+        self.generation += 1;
+        if let Some(overlay) = &mut self.overlay {
+            overlay.record_alive(&self.cells);
+        }
+        self.history
+            .record(EntryKind::Tick, flipped, self.generation, &self.cells);
+    }
+
+    /// Toggles cells by index, buffering the changes.
+    fn flip_cells(&mut self, indices: &[u32]) {
+        for &idx in indices {
+            let mut cell = self.cells[idx as usize];
+            cell.toggle();
+            self.set_cell_delta(idx as usize, cell);
+        }
+    }
+
+    /// Sets a cell by index, buffering the change.
+    fn set_cell_delta(&mut self, idx: usize, cell: Cell) {
+        self.cells[idx] = cell;
+        let (row, col) = self.get_rowcol(idx);
+        self.buffer_delta(row, col, cell);
     }
 
     fn live_neighbor_count(&self, row: u32, col: u32) -> u8 {
@@ -326,19 +754,17 @@ impl Universe {
             (south, east),
         ];
 
-        let count = neighbors
+        neighbors
             .into_iter()
             .map(|(r, c)| self.cells[self.get_index(r, c)] as u8)
-            .sum();
-
-        return count;
+            .sum()
     }
 
     fn live_neighbor_count_fixed(&self, row: u32, col: u32, boundary: Cell) -> u8 {
-        let north = (row != 0).then_some(row - 1);
-        let south = (row != self.height - 1).then_some(row + 1);
-        let west = (col != 0).then_some(col - 1);
-        let east = (col != self.width - 1).then_some(col + 1);
+        let north = row.checked_sub(1);
+        let south = (row + 1 < self.height).then_some(row + 1);
+        let west = col.checked_sub(1);
+        let east = (col + 1 < self.width).then_some(col + 1);
 
         let neighbors = [
             (north, west),
@@ -351,15 +777,13 @@ impl Universe {
             (south, east),
         ];
 
-        let count = neighbors
+        neighbors
             .into_iter()
             .map(|pair| match pair {
                 (Some(r), Some(c)) => self.cells[self.get_index(r, c)] as u8,
                 _ => boundary as u8,
             })
-            .sum();
-
-        return count;
+            .sum()
     }
 
     fn update_status(cell: Cell, live_neighbors: u8) -> Cell {
@@ -390,18 +814,126 @@ impl Universe {
     }
 }
 
-use std::{error::Error, fmt, mem};
+use std::{collections::BTreeMap, error::Error, fmt, mem};
 
 impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for line in self.cells.as_slice().chunks(self.width as usize) {
-            for &cell in line {
-                let symbol = if cell == Cell::Dead { '◻' } else { '◼' };
-                write!(f, "{}", symbol)?;
-            }
-            write!(f, "\n")?;
+        f.write_str(&self.render_text(&TextOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn glider() -> Universe {
+        let mut universe = Universe::new(6, 6);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        universe
+    }
+
+    #[test]
+    fn test_undo_tick() {
+        let mut universe = glider();
+        universe.set_history_budget(1 << 20);
+        let initial = universe.get_cells().to_vec();
+
+        universe.tick_delta();
+        let next = universe.get_cells().to_vec();
+        assert_eq!(1, universe.generation());
+
+        assert!(universe.undo());
+        assert_eq!(initial, universe.get_cells());
+        assert_eq!(0, universe.generation());
+        assert_eq!(universe.cells_born_count(), 2);
+        assert_eq!(universe.cells_died_count(), 2);
+
+        assert!(universe.redo());
+        assert_eq!(next, universe.get_cells());
+        assert_eq!(1, universe.generation());
+        assert!(!universe.redo());
+    }
+
+    #[test]
+    fn test_step_back_over_edits() {
+        let mut universe = glider();
+        universe.set_history_budget(1 << 20);
+        universe.tick();
+        let after_tick = universe.get_cells().to_vec();
+        universe.tick();
+        universe.toggle_cell(0, 0);
+        universe.toggle_cell(5, 5);
+
+        assert!(universe.step_back());
+        assert_eq!(after_tick, universe.get_cells());
+        assert_eq!(1, universe.generation());
+        assert_eq!(1, universe.history_position());
+    }
+
+    #[test]
+    fn test_seek_history_keyframes() {
+        let mut universe = glider();
+        universe.set_history_budget(1 << 20);
+        let mut states = vec![universe.get_cells().to_vec()];
+        for _ in 0..100 {
+            universe.tick();
+            states.push(universe.get_cells().to_vec());
         }
 
-        Ok(())
+        for position in [3, 97, 40, 0, 100, 64] {
+            assert!(universe.seek_history(position));
+            assert_eq!(states[position], universe.get_cells());
+            assert_eq!(position as u32, universe.generation());
+        }
+        assert!(!universe.seek_history(101));
+    }
+
+    #[test]
+    fn test_resize_anchors() {
+        let diagonal = || {
+            let mut universe = Universe::new(3, 3);
+            universe.set_cells(&[(0, 0), (1, 1), (2, 2)]);
+            universe
+        };
+
+        let mut universe = diagonal();
+        universe.resize(5, 4, Anchor::TopLeft);
+        assert_eq!(
+            "O....\n.O...\n..O..\n.....\n",
+            universe.region(0, 0, 5, 4).to_string()
+        );
+
+        let mut universe = diagonal();
+        universe.resize(5, 5, Anchor::Center);
+        assert_eq!(
+            ".....\n.O...\n..O..\n...O.\n.....\n",
+            universe.region(0, 0, 5, 5).to_string()
+        );
+
+        let mut universe = diagonal();
+        universe.resize(2, 2, Anchor::BottomRight);
+        assert_eq!("O.\n.O\n", universe.region(0, 0, 2, 2).to_string());
+        assert_eq!((2, 2), (universe.width(), universe.height()));
+    }
+
+    #[test]
+    fn test_resize_clears_deltas() {
+        let mut universe = glider();
+        universe.set_history_budget(1 << 20);
+        universe.tick_delta();
+        universe.resize(8, 8, Anchor::TopLeft);
+        assert_eq!(0, universe.cells_born_count());
+        assert_eq!(0, universe.cells_died_count());
+        assert_eq!(0, universe.history_len());
+        universe.tick_delta();
+        assert_eq!(8 * 8, universe.get_cells().len());
+    }
+
+    #[test]
+    fn test_history_disabled() {
+        let mut universe = glider();
+        universe.tick();
+        assert_eq!(0, universe.history_len());
+        assert!(!universe.undo());
     }
 }
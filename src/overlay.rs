@@ -0,0 +1,257 @@
+//! LifeHistory-style overlay for marking and tracing cells
+//!
+//! The overlay keeps a set of flags per cell alongside the universe. History
+//! and marks never affect evolution, while boundary cells behave as dead and
+//! are never born, as in LifeHistory. Flags map to
+//! the states of Golly's LifeHistory rule so patterns can round trip through
+//! RLE, see <https://conwaylife.com/wiki/LifeHistory>.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Universe};
+
+/// Overlay flag set on cells that have been alive since the overlay was
+/// enabled or its history cleared.
+pub const OVERLAY_HISTORY: u8 = 1 << 0;
+/// Overlay flag for cells marked by the user.
+pub const OVERLAY_MARK: u8 = 1 << 1;
+/// Overlay flag for boundary cells: visible, but always dead after a tick.
+pub const OVERLAY_BOUNDARY: u8 = 1 << 2;
+
+/// Number of states in the LifeHistory rule.
+pub const LIFE_HISTORY_STATES: u8 = 7;
+
+/// Converts a LifeHistory state into a cell and overlay flags.
+///
+/// Odd states are alive. State 5 (alive, start marked) is treated as a marked
+/// alive cell. Returns `None` if `state` is not less than
+/// [`LIFE_HISTORY_STATES`].
+pub fn from_life_history(state: u8) -> Option<(Cell, u8)> {
+    Some(match state {
+        0 => (Cell::Dead, 0),
+        1 => (Cell::Alive, OVERLAY_HISTORY),
+        2 => (Cell::Dead, OVERLAY_HISTORY),
+        3 | 5 => (Cell::Alive, OVERLAY_HISTORY | OVERLAY_MARK),
+        4 => (Cell::Dead, OVERLAY_HISTORY | OVERLAY_MARK),
+        6 => (Cell::Dead, OVERLAY_BOUNDARY),
+        _ => return None,
+    })
+}
+
+/// Converts a cell and overlay flags into the closest LifeHistory state.
+///
+/// Alive cells cannot be boundary cells in LifeHistory, so the flag is
+/// dropped for them.
+pub fn to_life_history(cell: Cell, flags: u8) -> u8 {
+    match cell {
+        Cell::Alive if flags & OVERLAY_MARK != 0 => 3,
+        Cell::Alive => 1,
+        Cell::Dead if flags & OVERLAY_BOUNDARY != 0 => 6,
+        Cell::Dead if flags & OVERLAY_MARK != 0 => 4,
+        Cell::Dead if flags & OVERLAY_HISTORY != 0 => 2,
+        Cell::Dead => 0,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Overlay {
+    pub flags: Vec<u8>,
+}
+
+impl Overlay {
+    pub fn new(cells: &[Cell]) -> Self {
+        let mut overlay = Overlay {
+            flags: vec![0; cells.len()],
+        };
+        overlay.record_alive(cells);
+        overlay
+    }
+
+    /// Sets the history flag on every alive cell.
+    pub fn record_alive(&mut self, cells: &[Cell]) {
+        for (flags, &cell) in self.flags.iter_mut().zip(cells) {
+            if cell == Cell::Alive {
+                *flags |= OVERLAY_HISTORY;
+            }
+        }
+    }
+}
+
+/// Overlay methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Whether the LifeHistory overlay is kept.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn overlay_enabled(&self) -> bool {
+        self.overlay.is_some()
+    }
+
+    /// Enables or disables the LifeHistory overlay.
+    ///
+    /// Enabling starts with the history of the currently alive cells and no
+    /// marks. Disabling discards all flags.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(setter))]
+    pub fn set_overlay_enabled(&mut self, enabled: bool) {
+        if enabled != self.overlay_enabled() {
+            self.overlay = enabled.then(|| Overlay::new(&self.cells));
+        }
+    }
+
+    /// Returns a pointer to the overlay flags buffer.
+    ///
+    /// Each cell has a `u8` of `OVERLAY_*` flags, laid out like
+    /// [`cells`](Self::cells). Null if the overlay is disabled.
+    pub fn overlay(&self) -> *const u8 {
+        self.overlay
+            .as_ref()
+            .map_or(std::ptr::null(), |o| o.flags.as_ptr())
+    }
+
+    /// Toggles the user mark on a cell, enabling the overlay if needed.
+    pub fn toggle_mark(&mut self, row: u32, col: u32) {
+        self.toggle_overlay_flag(row, col, OVERLAY_MARK);
+    }
+
+    /// Toggles a cell being a boundary cell, enabling the overlay if needed.
+    ///
+    /// Boundary cells are never born, and alive ones die on the next tick.
+    pub fn toggle_boundary(&mut self, row: u32, col: u32) {
+        self.toggle_overlay_flag(row, col, OVERLAY_BOUNDARY);
+    }
+
+    /// Forgets which cells have been alive, except the currently alive ones.
+    ///
+    /// Marks and boundary cells are kept.
+    pub fn clear_overlay_history(&mut self) {
+        if let Some(overlay) = &mut self.overlay {
+            for flags in &mut overlay.flags {
+                *flags &= !OVERLAY_HISTORY;
+            }
+            overlay.record_alive(&self.cells);
+        }
+    }
+
+    /// Removes all marks and boundary cells, keeping the history.
+    pub fn clear_marks(&mut self) {
+        if let Some(overlay) = &mut self.overlay {
+            for flags in &mut overlay.flags {
+                *flags &= OVERLAY_HISTORY;
+            }
+        }
+    }
+}
+
+impl Universe {
+    /// Get the overlay flags of all cells, if the overlay is enabled.
+    pub fn get_overlay(&self) -> Option<&[u8]> {
+        self.overlay.as_ref().map(|o| o.flags.as_slice())
+    }
+
+    /// Replaces the overlay flags, enabling the overlay.
+    ///
+    /// # Panics
+    ///
+    /// If `flags` does not have one entry per cell.
+    pub fn set_overlay(&mut self, flags: Vec<u8>) {
+        assert_eq!(self.cells.len(), flags.len());
+        self.overlay = Some(Overlay { flags });
+    }
+
+    /// Whether a cell is a boundary cell, which stays dead.
+    pub(crate) fn is_boundary(&self, idx: usize) -> bool {
+        self.overlay
+            .as_ref()
+            .is_some_and(|o| o.flags[idx] & OVERLAY_BOUNDARY != 0)
+    }
+
+    /// Toggles an overlay flag, buffering the cell so delta draws pick it up.
+    fn toggle_overlay_flag(&mut self, row: u32, col: u32, flag: u8) {
+        self.set_overlay_enabled(true);
+        let idx = self.get_index(row, col);
+        if let Some(overlay) = &mut self.overlay {
+            overlay.flags[idx] ^= flag;
+        }
+        self.buffer_delta(row, col, self.cells[idx]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_life_history_states_round_trip() {
+        for state in 0..LIFE_HISTORY_STATES {
+            let (cell, flags) = from_life_history(state).unwrap();
+            let expected = if state == 5 { 3 } else { state };
+            assert_eq!(expected, to_life_history(cell, flags), "{state}");
+        }
+        assert_eq!(None, from_life_history(LIFE_HISTORY_STATES));
+    }
+
+    #[test]
+    fn test_history_traces_glider() {
+        let mut universe = Universe::new(6, 6);
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        universe.set_overlay_enabled(true);
+        let before = universe.get_cells().to_vec();
+        for _ in 0..4 {
+            universe.tick();
+        }
+        let flags = universe.get_overlay().unwrap();
+        for (i, cell) in before.iter().chain(universe.get_cells()).enumerate() {
+            if *cell == Cell::Alive {
+                assert_ne!(0, flags[i % 36] & OVERLAY_HISTORY, "{i}");
+            }
+        }
+        // the cell the glider started on is still traced after it left
+        assert_eq!(Cell::Dead, universe.get_cells()[1]);
+        assert_eq!(OVERLAY_HISTORY, flags[1]);
+
+        universe.clear_overlay_history();
+        assert_eq!(0, universe.get_overlay().unwrap()[1]);
+    }
+
+    #[test]
+    fn test_marks_do_not_affect_evolution() {
+        let mut plain = Universe::new(5, 5);
+        plain.set_cells(&[(2, 1), (2, 2), (2, 3)]);
+        let mut marked = Universe::new(5, 5);
+        marked.set_cells(&[(2, 1), (2, 2), (2, 3)]);
+        marked.toggle_mark(2, 2);
+        marked.toggle_boundary(0, 0);
+        plain.tick();
+        marked.tick();
+        assert_eq!(plain.get_cells(), marked.get_cells());
+
+        let flags = marked.get_overlay().unwrap();
+        assert_eq!(OVERLAY_HISTORY | OVERLAY_MARK, flags[12]);
+        assert_eq!(OVERLAY_BOUNDARY, flags[0]);
+
+        marked.clear_marks();
+        assert_eq!(OVERLAY_HISTORY, marked.get_overlay().unwrap()[12]);
+    }
+
+    #[test]
+    fn test_boundary_cells_stay_dead() {
+        for delta in [false, true] {
+            // a horizontal blinker would be born at (1, 2) and (3, 2)
+            let mut universe = Universe::new(8, 8);
+            universe.set_cells(&[(2, 1), (2, 2), (2, 3), (5, 5), (5, 6), (6, 5), (6, 6)]);
+            universe.toggle_boundary(1, 2);
+            // an alive block cell dies when made a boundary
+            universe.toggle_boundary(5, 5);
+            if delta {
+                universe.tick_delta();
+            } else {
+                universe.tick();
+            }
+            let cells = universe.get_cells();
+            assert_eq!(Cell::Dead, cells[10], "{delta}");
+            assert_eq!(Cell::Alive, cells[26], "{delta}");
+            assert_eq!(Cell::Dead, cells[45], "{delta}");
+            assert_eq!(Cell::Alive, cells[46], "{delta}");
+        }
+    }
+}
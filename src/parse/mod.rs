@@ -8,7 +8,7 @@ use nom::{
     Err,
 };
 
-use crate::Cell;
+use crate::{from_life_history, Cell, LIFE_HISTORY_STATES};
 
 /// Wrapper to include files from the root patterns/ directory
 macro_rules! include_pattern {
@@ -25,8 +25,10 @@ mod plaintext;
 pub use plaintext::*;
 mod rle;
 pub use rle::*;
+mod rle_reader;
+pub use rle_reader::*;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// Immutable pattern storage
 pub struct Grid {
     pub width: usize,
@@ -73,9 +75,101 @@ impl Display for Grid {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Pattern storage with a state byte per cell, for multi-state rules
+///
+/// State 0 is dead. Two-state patterns only use state 1 otherwise, and
+/// LifeHistory patterns states 1 to 6.
+pub struct StateGrid {
+    pub width: usize,
+    pub height: usize,
+    pub states: Vec<u8>,
+    /// The rule given by the pattern, if any
+    pub rule: Option<String>,
+}
+
+impl StateGrid {
+    /// Whether the rule is LifeHistory, ignoring any bounded grid suffix.
+    pub fn is_life_history(&self) -> bool {
+        is_life_history_rule(self.rule.as_deref())
+    }
+
+    /// Converts to two-state cells, also returning overlay flags if the rule
+    /// is LifeHistory.
+    ///
+    /// Fails for cells other than dead or alive, unless the rule is
+    /// LifeHistory and they are LifeHistory states.
+    pub fn to_grid(&self) -> Result<(Grid, Option<Vec<u8>>), MultiStateError> {
+        let limit = loadable_states(self.rule.as_deref());
+        if let Some(&state) = self.states.iter().find(|&&s| s >= limit) {
+            return Err(MultiStateError { state });
+        }
+        let (cells, flags) = self
+            .states
+            .iter()
+            .map(|&s| from_life_history(s).expect("states are checked above"))
+            .unzip();
+        let grid = Grid {
+            width: self.width,
+            height: self.height,
+            cells,
+        };
+        Ok((grid, self.is_life_history().then_some(flags)))
+    }
+}
+
+/// Whether an RLE rule is LifeHistory, ignoring any bounded grid suffix.
+pub(crate) fn is_life_history_rule(rule: Option<&str>) -> bool {
+    rule.is_some_and(|rule| {
+        let name = rule.split(':').next().unwrap_or_default();
+        name.eq_ignore_ascii_case(LIFE_HISTORY_RULE)
+    })
+}
+
+/// Number of states that load into two-state grids under a rule, with
+/// LifeHistory states kept as overlay flags.
+pub(crate) fn loadable_states(rule: Option<&str>) -> u8 {
+    if is_life_history_rule(rule) {
+        LIFE_HISTORY_STATES
+    } else {
+        2
+    }
+}
+
+impl From<&Grid> for StateGrid {
+    fn from(grid: &Grid) -> Self {
+        StateGrid {
+            width: grid.width,
+            height: grid.height,
+            states: grid.cells.iter().map(|&cell| cell as u8).collect(),
+            rule: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A multi-state cell that two-state grids and universes cannot hold
+pub struct MultiStateError {
+    pub state: u8,
+}
+
+impl std::error::Error for MultiStateError {}
+
+impl Display for MultiStateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pattern has cells in state {}, but only two-state and LifeHistory \
+            patterns can be loaded",
+            self.state
+        )
+    }
+}
+
 trait LifeParser {
     /// File extensions that may indicate the parser can be used
     // const MIME_TYPES: [&'static str];
+    #[allow(dead_code)]
     fn file_extensions(&self) -> &[&str];
 
     /// Quick, low-overhead method to detect if the file is the format
@@ -88,6 +182,12 @@ trait LifeParser {
     }
 
     fn parse(&self, input: &str) -> Result<Grid, ParseError>;
+
+    /// Parses the pattern along with LifeHistory overlay flags, if the format
+    /// has any.
+    fn parse_with_overlay(&self, input: &str) -> Result<(Grid, Option<Vec<u8>>), ParseError> {
+        self.parse(input).map(|grid| (grid, None))
+    }
 }
 
 const PARSERS: &[&'static dyn LifeParser] = &[&PlaintextParser(), &RleParser()];
@@ -98,7 +198,17 @@ pub fn parse_str(input: &str) -> Result<Grid, ParseError> {
             return parser.parse(input);
         }
     }
-    panic!() // TODO: error
+    Err(ParseError::unknown_format())
+}
+
+/// Like [`parse_str`], also returning LifeHistory overlay flags if present.
+pub fn parse_str_with_overlay(input: &str) -> Result<(Grid, Option<Vec<u8>>), ParseError> {
+    for parser in PARSERS {
+        if parser.sniff(input) {
+            return parser.parse_with_overlay(input);
+        }
+    }
+    Err(ParseError::unknown_format())
 }
 
 /// Drop-in replacement to IResult that holds VerboseErrors
@@ -116,6 +226,11 @@ impl ParseError {
             convert_error(source, error)
         ))
     }
+
+    /// Error for input none of the parsers recognise.
+    fn unknown_format() -> Self {
+        Self("Error parsing input: unknown pattern format".to_owned())
+    }
 }
 
 impl std::error::Error for ParseError {}
@@ -16,7 +16,7 @@ use nom::{
 
 pub struct PlaintextParser();
 
-const FILE_EXTENSIONS: &'static [&'static str] = &["cells"];
+const FILE_EXTENSIONS: &[&str] = &["cells"];
 
 impl LifeParser for PlaintextParser {
     fn file_extensions(&self) -> &[&str] {
@@ -40,6 +40,12 @@ pub fn parse_plaintext(input: &str) -> Result<Grid, ParseError> {
     Ok(grid)
 }
 
+/// Writes a grid as plaintext, keeping trailing dead cells so the dimensions
+/// survive a round trip.
+pub fn write_plaintext(grid: &Grid) -> String {
+    format!("!\n{grid}")
+}
+
 fn normalize_rows(rows: Vec<Vec<Cell>>) -> Grid {
     let height = rows.len();
     let width = rows.iter().map(|r| r.len()).max().unwrap_or_default();
@@ -100,6 +106,14 @@ mod test {
         assert_eq!(Ok(expected), parsed.map(|g| g.to_string()))
     }
 
+    #[test]
+    fn test_write_round_trip() {
+        let grid = parse_plaintext("!\n.O...\n..O..\nOOO..\n.....\n").unwrap();
+        let written = write_plaintext(&grid);
+        assert_eq!("!\n.O...\n..O..\nOOO..\n.....\n", written);
+        assert_eq!(Ok(grid), parse_plaintext(&written));
+    }
+
     #[test]
     fn test_plaintext_glider() {
         assert!(GLIDER.ends_with("\n"));
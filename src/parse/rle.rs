@@ -1,34 +1,79 @@
-//! Parsing of run length encoded (.cells) gol files
+//! Parsing and writing of run length encoded (.rle) gol files
 //!
 //! Based on information from <https://conwaylife.com/wiki/Run_Length_Encoded>.
+//! Besides `b` and `o`, cells may use the multi-state letters `.`, `A`-`X`
+//! and `pA`-`yO` for states 0 to 255, read by [`parse_rle_states`]. States of
+//! Golly's LifeHistory rule also load into two-state grids with an overlay,
+//! under that rule only.
 
 use super::*;
 
-use std::str::FromStr;
+use std::{fmt::Write, str::FromStr};
+
+use crate::{to_life_history, RULE};
 
 use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{
-        alphanumeric1, char, digit1, line_ending, multispace0, not_line_ending, space0,
+        anychar, char, digit1, line_ending, multispace0, not_line_ending, satisfy, space0,
     },
-    combinator::{map, map_res, opt, peek, rest, value},
-    error::{context, ErrorKind, FromExternalError},
-    multi::{many0, separated_list0},
+    combinator::{map, map_opt, map_res, opt, peek, rest, value},
+    error::{context, VerboseErrorKind},
+    multi::many0,
     sequence::{pair, preceded, separated_pair, terminated, tuple},
-    Finish, Parser,
+    Finish,
 };
 
 pub struct RleParser();
 
-const FILE_EXTENSIONS: &'static [&'static str] = &["rle"];
+const FILE_EXTENSIONS: &[&str] = &["rle"];
 
-mod tags {
+pub(super) mod tags {
     pub const DEAD: char = 'b';
     pub const ALIVE: char = 'o';
     pub const EOL: char = '$';
+    /// Dead cell in multi-state rules
+    pub const STATE_0: char = '.';
+    /// Letter of multi-state state 1, followed by `B` for state 2 and so on
+    pub const STATE_1: char = 'A';
+    /// Last single letter state, 24
+    pub const STATE_LAST: char = 'X';
+    /// Prefixes of states 25 to 48, 49 to 72 and so on
+    pub const PREFIXES: std::ops::RangeInclusive<char> = 'p'..='y';
+}
+
+/// Error contexts for malformed cells, shared with the streaming reader
+pub(super) mod errors {
+    pub const UNKNOWN_TAG: &str = "unknown tag";
+    pub const COUNT_WITHOUT_TAG: &str = "run count without a tag";
+    pub const COUNT_TOO_LARGE: &str = "run count too large";
+    pub const MISSING_END: &str = "missing `!` at the end of the cells";
+    pub const ROW_TOO_WIDE: &str = "row wider than the pattern";
+    pub const TOO_MANY_ROWS: &str = "more rows than the pattern's height";
+}
+
+/// State of a multi-state letter and its prefix, if it is in 1 to 255.
+pub(super) fn multi_state(prefix: Option<char>, letter: char) -> Option<u8> {
+    let letters = tags::STATE_1..=tags::STATE_LAST;
+    if !letters.contains(&letter) {
+        return None;
+    }
+    let high = match prefix {
+        None => 0,
+        Some(p) if tags::PREFIXES.contains(&p) => p as u32 - *tags::PREFIXES.start() as u32 + 1,
+        Some(_) => return None,
+    };
+    let low = letter as u32 - tags::STATE_1 as u32 + 1;
+    (high * letters.count() as u32 + low).try_into().ok()
 }
 
+/// Name of the LifeHistory rule in RLE headers.
+pub(crate) const LIFE_HISTORY_RULE: &str = "LifeHistory";
+
+/// Longest line written, as recommended by the format.
+const MAX_LINE_LENGTH: usize = 70;
+
 impl LifeParser for RleParser {
     fn file_extensions(&self) -> &[&str] {
         FILE_EXTENSIONS
@@ -41,97 +86,282 @@ impl LifeParser for RleParser {
     fn parse(&self, input: &str) -> Result<Grid, ParseError> {
         parse_rle(input)
     }
+
+    fn parse_with_overlay(&self, input: &str) -> Result<(Grid, Option<Vec<u8>>), ParseError> {
+        parse_rle_with_overlay(input)
+    }
+}
+
+/// How strictly RLE files are read.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RleOptions {
+    /// Grow the pattern to fit cells outside the header's size and allow a
+    /// missing `!`, as Golly does
+    pub lenient: bool,
 }
 
 pub fn parse_rle(input: &str) -> Result<Grid, ParseError> {
-    let (_rest, rle) = context("rle", rle)(input)
-        .finish()
-        .map_err(|e| ParseError::new(e, input))?;
+    parse_rle_with_overlay(input).map(|(grid, _overlay)| grid)
+}
+
+/// Parses an RLE file, also returning overlay flags for LifeHistory states.
+///
+/// The flags are only returned if the rule is LifeHistory. Cells other than
+/// dead or alive are errors under any other rule.
+pub fn parse_rle_with_overlay(input: &str) -> Result<(Grid, Option<Vec<u8>>), ParseError> {
+    parse_rle_with_options(input, &RleOptions::default())
+}
+
+/// Like [`parse_rle_with_overlay`], optionally reading malformed files
+/// leniently.
+pub fn parse_rle_with_options(
+    input: &str,
+    options: &RleOptions,
+) -> Result<(Grid, Option<Vec<u8>>), ParseError> {
+    let rle = read_rle(input, options)?;
+    let limit = loadable_states(rle.rule);
+    let error = rle.tag_seq.iter().find_map(|&(_, tag, at)| match tag {
+        RleTag::State(state) if state >= limit => Some(VerboseError {
+            errors: vec![(at, VerboseErrorKind::Context(TOO_MANY_STATES))],
+        }),
+        _ => None,
+    });
+    if let Some(e) = error {
+        return Err(ParseError::new(e, input));
+    }
     let grid = rle
-        .try_into()
-        .map_err(|e| VerboseError::from_external_error(input, ErrorKind::Fail, e))
+        .state_grid(options.lenient)
         .map_err(|e| ParseError::new(e, input))?;
-    Ok(grid)
+    Ok(grid.to_grid().expect("states are checked above"))
+}
+
+/// Parses an RLE file of any rule, keeping the state of each cell.
+pub fn parse_rle_states(input: &str, options: &RleOptions) -> Result<StateGrid, ParseError> {
+    read_rle(input, options)?
+        .state_grid(options.lenient)
+        .map_err(|e| ParseError::new(e, input))
 }
 
-type RuleSet<'a> = (&'a str, &'a str);
+/// Most cells in a parsed pattern, so hostile input cannot exhaust memory
+const MAX_CELLS: usize = 1 << 28;
 
-struct Rle {
-    width: usize,
-    height: usize,
-    tag_seq: Vec<(usize, char)>,
+/// Error context for multi-state cells in two-state patterns
+const TOO_MANY_STATES: &str =
+    "multi-state cell outside of LifeHistory, which two-state grids cannot hold";
+
+fn read_rle<'a>(input: &'a str, options: &RleOptions) -> Result<Rle<'a>, ParseError> {
+    let (_rest, rle) = context("rle", |i| rle(i, options.lenient))(input)
+        .finish()
+        .map_err(|e| ParseError::new(e, input))?;
+    Ok(rle)
+}
+
+/// Writes a grid as RLE.
+pub fn write_rle(grid: &Grid) -> String {
+    write_rle_states(grid, RULE, tags::DEAD, |i| match grid.cells[i] {
+        Cell::Alive => tags::ALIVE,
+        Cell::Dead => tags::DEAD,
+    })
 }
 
-impl TryInto<Grid> for Rle {
-    type Error = ParseError;
+/// Writes a grid with overlay flags as LifeHistory RLE.
+///
+/// # Panics
+///
+/// If `overlay` does not have one entry per cell.
+pub fn write_rle_with_overlay(grid: &Grid, overlay: &[u8]) -> String {
+    assert_eq!(grid.cells.len(), overlay.len());
+    write_rle_states(grid, LIFE_HISTORY_RULE, tags::STATE_0, |i| {
+        state_tag(to_life_history(grid.cells[i], overlay[i]))
+    })
+}
 
-    fn try_into(self) -> Result<Grid, Self::Error> {
-        let Rle {
-            width,
-            height,
-            tag_seq,
-        } = self;
+/// Letter of a multi-state state.
+fn state_tag(state: u8) -> char {
+    match state {
+        0 => tags::STATE_0,
+        s => (tags::STATE_1 as u8 + s - 1) as char,
+    }
+}
+
+/// Writes the run length encoded cells of a grid without the header line.
+pub(crate) fn write_rle_body(grid: &Grid) -> String {
+    let mut out = String::new();
+    write_rle_tags(&mut out, grid, tags::DEAD, |i| match grid.cells[i] {
+        Cell::Alive => tags::ALIVE,
+        Cell::Dead => tags::DEAD,
+    });
+    out
+}
+
+/// Writes the LifeHistory run length encoded cells and overlay flags of a
+/// grid without the header line.
+#[cfg(feature = "serde")]
+pub(crate) fn write_rle_body_with_overlay(grid: &Grid, overlay: &[u8]) -> String {
+    assert_eq!(grid.cells.len(), overlay.len());
+    let mut out = String::new();
+    write_rle_tags(&mut out, grid, tags::STATE_0, |i| {
+        state_tag(to_life_history(grid.cells[i], overlay[i]))
+    });
+    out
+}
+
+/// Writes the header and run length encoded tags of each cell by index.
+fn write_rle_states(grid: &Grid, rule: &str, dead: char, tag: impl Fn(usize) -> char) -> String {
+    let mut out = format!("x = {}, y = {}, rule = {}\n", grid.width, grid.height, rule);
+    write_rle_tags(&mut out, grid, dead, tag);
+    out
+}
+
+/// Appends the run length encoded tags of each cell by index.
+///
+/// Runs of `dead` at the end of rows and empty rows at the end are left out.
+fn write_rle_tags(out: &mut String, grid: &Grid, dead: char, tag: impl Fn(usize) -> char) {
+    let mut line_len = 0;
+    let mut push = |out: &mut String, count: usize, tag: char| {
+        let start = out.len();
+        if count > 1 {
+            write!(out, "{count}").unwrap();
+        }
+        out.push(tag);
+        let len = out.len() - start;
+        if line_len + len > MAX_LINE_LENGTH {
+            out.insert(start, '\n');
+            line_len = 0;
+        }
+        line_len += len;
+    };
+
+    let mut current_row = 0;
+    let mut runs: Vec<(usize, char)> = Vec::new();
+    for y in 0..grid.height {
+        runs.clear();
+        for i in y * grid.width..(y + 1) * grid.width {
+            let tag = tag(i);
+            match runs.last_mut() {
+                Some((count, last)) if *last == tag => *count += 1,
+                _ => runs.push((1, tag)),
+            }
+        }
+        if runs.last().is_some_and(|&(_, tag)| tag == dead) {
+            runs.pop();
+        }
+        if runs.is_empty() {
+            continue;
+        }
+
+        if y > current_row {
+            push(out, y - current_row, tags::EOL);
+            current_row = y;
+        }
+        for &(count, tag) in &runs {
+            push(out, count, tag);
+        }
+    }
+    push(out, 1, '!');
+    out.push('\n');
+}
+
+/// Cell state or end of line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RleTag {
+    State(u8),
+    Eol,
+}
 
-        // TODO: turn panics into errors or handle softly...
-        // TODO: validate tag seq to size?
+/// Run count, tag and the input starting at the tag, for error positions
+type Tag<'a> = (usize, RleTag, &'a str);
 
-        let mut cells = Vec::with_capacity(width * height);
+struct Rle<'a> {
+    /// The input starting at the header, for size errors
+    header: &'a str,
+    width: usize,
+    height: usize,
+    rule: Option<&'a str>,
+    tag_seq: Vec<Tag<'a>>,
+}
 
-        for (i, tag) in tag_seq {
+impl<'a> Rle<'a> {
+    /// Expands the tags into one state per cell.
+    ///
+    /// Cells other than dead ones outside the header's size are errors,
+    /// unless `lenient`, in which case the size grows to fit them.
+    /// Patterns of more than [`MAX_CELLS`] cells are errors either way.
+    fn state_grid(&self, lenient: bool) -> Result<StateGrid, VerboseError<&'a str>> {
+        let error = |at, message| VerboseError {
+            errors: vec![(at, VerboseErrorKind::Context(message))],
+        };
+
+        let (mut width, mut height) = (self.width, self.height);
+        // where the size was last set, to blame if it's too large
+        let mut size_at = self.header;
+        let (mut row, mut col) = (0usize, 0usize);
+        // runs of cells other than dead as (row, col, len, state)
+        let mut runs = Vec::new();
+        for &(count, tag, at) in &self.tag_seq {
             let state = match tag {
-                tags::EOL => {
-                    // TODO: if already at end of line, skip?
-                    for i in 0..i {
-                        // fill to end of line
-                        let row = cells.len() % width;
-                        if i == 0 && row == 0 {
-                            // already at end of line
-                            continue;
-                        }
-                        for _ in 0..width - row {
-                            cells.push(Cell::Dead);
-                        }
-                    }
+                RleTag::Eol => {
+                    row = row
+                        .checked_add(count)
+                        .ok_or_else(|| error(at, errors::COUNT_TOO_LARGE))?;
+                    col = 0;
                     continue;
                 }
-                tags::ALIVE => Cell::Alive,
-                tags::DEAD => Cell::Dead,
-                t => panic!("unexpected tag {t:?}"),
+                RleTag::State(state) => state,
             };
-
-            for _ in 0..i {
-                cells.push(state);
+            let start = col;
+            col = col
+                .checked_add(count)
+                .ok_or_else(|| error(at, errors::COUNT_TOO_LARGE))?;
+            if state == 0 {
+                continue;
             }
+            if lenient {
+                let rows = row
+                    .checked_add(1)
+                    .ok_or_else(|| error(at, errors::COUNT_TOO_LARGE))?;
+                if col > width || rows > height {
+                    (width, height, size_at) = (width.max(col), height.max(rows), at);
+                }
+            } else if col > width {
+                return Err(error(at, errors::ROW_TOO_WIDE));
+            } else if row >= height {
+                return Err(error(at, errors::TOO_MANY_ROWS));
+            }
+            runs.push((row, start, count, state));
         }
 
-        // fill remaining rows
-        for _ in cells.len()..(width * height) {
-            cells.push(Cell::Dead);
+        let cells = width
+            .checked_mul(height)
+            .filter(|&cells| cells <= MAX_CELLS)
+            .ok_or_else(|| error(size_at, "pattern too large"))?;
+        let mut states = vec![0; cells];
+        for (row, col, len, state) in runs {
+            let start = row * width + col;
+            states[start..start + len].fill(state);
         }
-
-        assert_eq!(cells.len(), (width * height));
-
-        Ok(Grid {
+        Ok(StateGrid {
             width,
             height,
-            cells,
+            states,
+            rule: self.rule.map(str::to_owned),
         })
     }
 }
 
-fn rle(i: &str) -> VIResult<&str, Rle> {
-    let (i, _comments) = context(
-        "comments",
-        terminated(separated_list0(line_ending, hash_comment), line_ending),
-    )(i)?;
-    let (i, (width, height, _rule_set)) = context("header", terminated(header, line_ending))(i)?;
-    let (i, tag_seq) = context("cells", cells)(i)?;
+fn rle(i: &str, lenient: bool) -> VIResult<&str, Rle<'_>> {
+    let (i, _comments) = context("comments", many0(terminated(hash_comment, line_ending)))(i)?;
+    let header_at = i;
+    let (i, (width, height, rule)) = context("header", terminated(header, line_ending))(i)?;
+    let (i, tag_seq) = context("cells", |i| cells(i, lenient))(i)?;
     let (i, _trailing_comments) =
         context("trailing comments", opt(preceded(line_ending, rest)))(i)?;
 
     let rle = Rle {
+        header: header_at,
         width,
         height,
+        rule,
         tag_seq,
     };
     Ok((i, rle))
@@ -141,10 +371,10 @@ fn hash_comment(i: &str) -> VIResult<&str, ()> {
     context("comment", value((), pair(char('#'), opt(not_line_ending))))(i)
 }
 
-fn header(i: &str) -> VIResult<&str, (usize, usize, Option<RuleSet>)> {
+pub(super) fn header(i: &str) -> VIResult<&str, (usize, usize, Option<&str>)> {
     let width = context("width", map_res(kv(char('x'), digit1), usize::from_str));
     let height = context("height", map_res(kv(char('y'), digit1), usize::from_str));
-    let rule = context("rule_set", kv(tag("rule"), rule_set));
+    let rule = context("rule", kv(tag("rule"), rule));
 
     let (i, (w, h)) = separated_pair(width, char(','), height)(i)?;
     let (i, r) = opt(preceded(char(','), rule))(i)?;
@@ -153,36 +383,58 @@ fn header(i: &str) -> VIResult<&str, (usize, usize, Option<RuleSet>)> {
     Ok((i, (w, h, r)))
 }
 
-fn rle_tag(i: &str) -> VIResult<&str, char> {
-    let alive = char(tags::ALIVE);
-    let dead = char(tags::DEAD);
-    let eol = char(tags::EOL);
-    context("rle tag", alt((alive, dead, eol)))(i)
+fn rle_tag(i: &str) -> VIResult<&str, RleTag> {
+    let alive = value(RleTag::State(1), char(tags::ALIVE));
+    let dead = value(
+        RleTag::State(0),
+        alt((char(tags::DEAD), char(tags::STATE_0))),
+    );
+    let eol = value(RleTag::Eol, char(tags::EOL));
+    let prefix = satisfy(|c| tags::PREFIXES.contains(&c));
+    let state = map_opt(pair(opt(prefix), anychar), |(prefix, letter)| {
+        multi_state(prefix, letter).map(RleTag::State)
+    });
+    context("rle tag", alt((alive, dead, eol, state)))(i)
 }
 
-fn cells(i: &str) -> VIResult<&str, Vec<(usize, char)>> {
-    let end_cells = context("cell end", char('!'));
-
+fn cells(i: &str, lenient: bool) -> VIResult<&str, Vec<Tag<'_>>> {
     let count = context("count", map_res(digit1, usize::from_str));
+    let at = |i| Ok((i, i));
+    let tag = map(tuple((at, opt(count), rle_tag)), |(at, count, tag)| {
+        (count.unwrap_or(1), tag, at)
+    });
 
-    let repeated_cell = count.and(rle_tag);
-    let single_cell = map(rle_tag, |v| (1, v));
+    let tags = context("rle tags", many0(preceded(multispace0, tag)));
 
-    let tags = context(
-        "rle tags",
-        many0(preceded(multispace0, repeated_cell.or(single_cell))),
-    );
-
-    let mut lines = terminated(tags, preceded(multispace0, end_cells));
+    terminated(tags, preceded(multispace0, |i| cells_end(i, lenient)))(i)
+}
 
-    lines(i)
+/// Matches the `!` after the cells, or failing that names what was found
+/// instead.
+fn cells_end(i: &str, lenient: bool) -> VIResult<&str, ()> {
+    // point past a count to the tag it lacks
+    let at = i.trim_start_matches(|c: char| c.is_ascii_digit());
+    let count = &i[..i.len() - at.len()];
+    if !count.is_empty() && count.parse::<usize>().is_err() {
+        return Err(Err::Failure(VerboseError {
+            errors: vec![(i, VerboseErrorKind::Context(errors::COUNT_TOO_LARGE))],
+        }));
+    }
+    let message = match at.chars().next() {
+        Some('!') if at.len() == i.len() => return Ok((&i[1..], ())),
+        None if lenient && i.is_empty() => return Ok((i, ())),
+        Some('!') => errors::COUNT_WITHOUT_TAG,
+        None => errors::MISSING_END,
+        _ => errors::UNKNOWN_TAG,
+    };
+    Err(Err::Failure(VerboseError {
+        errors: vec![(at, VerboseErrorKind::Context(message))],
+    }))
 }
 
-fn rule_set(i: &str) -> VIResult<&str, RuleSet> {
-    context(
-        "rule",
-        separated_pair(alphanumeric1, char('/'), alphanumeric1),
-    )(i)
+/// The rest of the header line, as rules may have suffixes like `:T10,10`
+fn rule(i: &str) -> VIResult<&str, &str> {
+    context("rule", map(not_line_ending, str::trim_end))(i)
 }
 
 /// ` {key} = {value} ` with whitespace handling, returning `value`
@@ -197,9 +449,31 @@ where
     )
 }
 
+/// Inputs with malformed cells, the error and a line of input it shows
+#[cfg(test)]
+pub(super) const MALFORMED_CELLS: [(&str, &str, &str); 8] = [
+    ("x = 2, y = 2\nbo$2bo!", errors::ROW_TOO_WIDE, "2bo!"),
+    ("x = 2, y = 2\nbo$o$o!", errors::TOO_MANY_ROWS, "o!"),
+    ("x = 2, y = 2\nbo$\nbz!", errors::UNKNOWN_TAG, "bz!"),
+    ("x = 2, y = 2\nbo$2o\n", errors::MISSING_END, ""),
+    ("x = 2, y = 2\nbo$2!", errors::COUNT_WITHOUT_TAG, "2!"),
+    ("x = 2, y = 2\nbyP!", errors::UNKNOWN_TAG, "byP!"),
+    (
+        "x = 2, y = 2\n18446744073709551615b18446744073709551615o!",
+        errors::COUNT_TOO_LARGE,
+        "18446744073709551615o!",
+    ),
+    (
+        "x = 2, y = 2\n18446744073709551616o!",
+        errors::COUNT_TOO_LARGE,
+        "18446744073709551616o!",
+    ),
+];
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{Universe, OVERLAY_BOUNDARY, OVERLAY_HISTORY, OVERLAY_MARK};
 
     #[test]
     fn test_parse() {
@@ -229,24 +503,175 @@ mod test {
     fn test_header_rules() {
         let input = " x = 1, y = 3, rule=3B/4a\nblah blah";
         let rest = "\nblah blah";
-        let output = (1, 3, Some(("3B", "4a")));
+        let output = (1, 3, Some("3B/4a"));
         assert_eq!(Ok((rest, output)), header(input));
     }
 
     #[test]
     fn test_cells() {
-        use tags::*;
-        let input = "2b2o$bobo!";
+        use RleTag::*;
+        let input = "2b2o$bo.A!";
         let rest = "";
         let output = vec![
-            (2, DEAD),
-            (2, ALIVE),
-            (1, EOL),
-            (1, DEAD),
-            (1, ALIVE),
-            (1, DEAD),
-            (1, ALIVE),
+            (2, State(0)),
+            (2, State(1)),
+            (1, Eol),
+            (1, State(0)),
+            (1, State(1)),
+            (1, State(0)),
+            (1, State(1)),
+        ];
+        let (i, tags) = cells(input, false).unwrap();
+        let tags: Vec<_> = tags
+            .into_iter()
+            .map(|(count, tag, _at)| (count, tag))
+            .collect();
+        assert_eq!((rest, output), (i, tags));
+    }
+
+    #[test]
+    fn test_blank_rows() {
+        let grid = parse_rle("x = 3, y = 4\n3o$$3o2$!\n").unwrap();
+        assert_eq!("OOO\n...\nOOO\n...\n", grid.to_string());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |input| parse_rle(input).unwrap_err().to_string();
+        let cases = [
+            ("x = 2, y = 2\nbG!", TOO_MANY_STATES, "bG!"),
+            ("x = 2, y = 2\nbpA!", TOO_MANY_STATES, "bpA!"),
         ];
-        assert_eq!(Ok((rest, output)), cells(input));
+        for (input, message, line) in MALFORMED_CELLS.into_iter().chain(cases) {
+            let error = error(input);
+            assert!(error.contains(message), "{input:?}: {error}");
+            assert!(error.contains("at line 2"), "{input:?}: {error}");
+            assert!(error.contains(line), "{input:?}: {error}");
+        }
+
+        for input in [
+            "x = 4294967296, y = 4294967296\n!",
+            "x = 4294967295, y = 4294967295\n!",
+        ] {
+            let error = error(input);
+            assert!(error.contains("pattern too large"), "{input:?}: {error}");
+            assert!(error.contains("at line 1"), "{input:?}: {error}");
+        }
+        let lenient = RleOptions { lenient: true };
+        let Err(error) = parse_rle_with_options("x = 0, y = 0\n4294967296$4294967296bo!", &lenient)
+        else {
+            panic!("loaded a pattern too large for memory");
+        };
+        let error = error.to_string();
+        assert!(error.contains("pattern too large"), "{error}");
+        assert!(error.contains("at line 2"), "{error}");
+        assert!(error.contains("o!"), "{error}");
+        assert!(parse_str("not a pattern").is_err());
+    }
+
+    #[test]
+    fn test_multi_state() {
+        let input = "x = 4, y = 2, rule = B3/S23/C256\n.AXpA$yObo!\n";
+        let grid = parse_rle_states(input, &RleOptions::default()).unwrap();
+        assert_eq!(vec![0, 1, 24, 25, 255, 0, 1, 0], grid.states);
+        assert_eq!(Some("B3/S23/C256"), grid.rule.as_deref());
+        assert_eq!(Err(MultiStateError { state: 24 }), grid.to_grid());
+        let Err(error) = Universe::of_state_grid(&grid) else {
+            panic!("loaded a multi-state pattern");
+        };
+        let error = error.to_string();
+        assert!(error.contains("state 24"), "{error}");
+
+        // two-state patterns convert without an overlay
+        let input = "x = 3, y = 1, rule = B3/S23:T3,1\nobo!\n";
+        let grid = parse_rle_states(input, &RleOptions::default()).unwrap();
+        let (cells, overlay) = grid.to_grid().unwrap();
+        assert_eq!((parse_rle(input).unwrap(), None), (cells.clone(), overlay));
+        let rule = Some("B3/S23:T3,1".to_owned());
+        assert_eq!(
+            grid,
+            StateGrid {
+                rule,
+                ..StateGrid::from(&cells)
+            }
+        );
+
+        // LifeHistory letters are only LifeHistory states under that rule
+        for input in [
+            "x = 2, y = 1, rule = B2/S/C3\nAB!\n",
+            "x = 2, y = 1, rule = B3/S23\nAB!\n",
+        ] {
+            let grid = parse_rle_states(input, &RleOptions::default()).unwrap();
+            assert_eq!(vec![1, 2], grid.states);
+            assert_eq!(Err(MultiStateError { state: 2 }), grid.to_grid());
+            assert!(Universe::of_state_grid(&grid).is_err());
+            let error = parse_rle_with_overlay(input).unwrap_err().to_string();
+            assert!(error.contains(TOO_MANY_STATES), "{input:?}: {error}");
+            assert!(error.contains("B!"), "{input:?}: {error}");
+        }
+
+        let history = "x = 2, y = 1, rule = LifeHistory:T2,1\nBC!\n";
+        let grid = parse_rle_states(history, &RleOptions::default()).unwrap();
+        assert!(grid.is_life_history());
+        assert_eq!(
+            parse_rle_with_overlay(history).unwrap(),
+            grid.to_grid().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_lenient() {
+        let lenient = RleOptions { lenient: true };
+        let (grid, _) = parse_rle_with_options("x = 2, y = 1\nbo$3o$o", &lenient).unwrap();
+        assert_eq!(".O.\nOOO\nO..\n", grid.to_string());
+        let (grid, _) = parse_rle_with_options("x = 0, y = 0\n2o!", &lenient).unwrap();
+        assert_eq!("OO\n", grid.to_string());
+        // letters outside the rule are still errors
+        assert!(parse_rle_with_options("x = 2, y = 2\nbz!", &lenient).is_err());
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let input = include_pattern!("p43gliderloop.rle");
+        let grid = parse_rle(input).unwrap();
+        let output = write_rle(&grid);
+        assert!(output.lines().all(|l| l.len() <= MAX_LINE_LENGTH));
+        assert_eq!(grid, parse_rle(&output).unwrap());
+    }
+
+    #[test]
+    fn test_write_blank_rows() {
+        let grid = parse_plaintext("!blank rows\n..\n..\n.O\n..\n").unwrap();
+        let output = write_rle(&grid);
+        assert_eq!("x = 2, y = 4, rule = B3/S23\n2$bo!\n", output);
+        assert_eq!(grid, parse_rle(&output).unwrap());
+    }
+
+    #[test]
+    fn test_life_history() {
+        let input = "x = 4, y = 2, rule = LifeHistory\n.ABC$DEF!\n";
+        let (grid, overlay) = parse_rle_with_overlay(input).unwrap();
+        assert_eq!(".O.O\n.O..\n", grid.to_string());
+        let overlay = overlay.unwrap();
+        assert_eq!(
+            vec![
+                0,
+                OVERLAY_HISTORY,
+                OVERLAY_HISTORY,
+                OVERLAY_HISTORY | OVERLAY_MARK,
+                OVERLAY_HISTORY | OVERLAY_MARK,
+                OVERLAY_HISTORY | OVERLAY_MARK,
+                OVERLAY_BOUNDARY,
+                0,
+            ],
+            overlay
+        );
+
+        // state 5 is written back as state 3
+        let output = write_rle_with_overlay(&grid, &overlay);
+        assert_eq!("x = 4, y = 2, rule = LifeHistory\n.ABC$DCF!\n", output);
+
+        let plain = parse_rle_with_overlay("x = 2, y = 1\nbo!\n").unwrap();
+        assert_eq!(None, plain.1);
     }
 }
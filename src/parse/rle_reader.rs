@@ -0,0 +1,439 @@
+//! Streaming reading of run length encoded (.rle) gol files
+//!
+//! [`RleReader`] reads the header up front, then yields runs of live cells a
+//! line of input at a time, without allocating a cell per position. Huge but
+//! sparse patterns can be fed into sparse storage, and oversized ones
+//! rejected before reading any cells.
+
+use std::fmt::Display;
+use std::io::{self, BufRead};
+
+use nom::Err;
+
+use super::rle::{errors, header, multi_state, tags};
+
+/// Dimensions, rule and comments from the start of an RLE file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RleHeader {
+    pub width: usize,
+    pub height: usize,
+    /// The `rule` as written, if given
+    pub rule: Option<String>,
+    /// `#` lines before the header, without the `#`
+    pub comments: Vec<String>,
+}
+
+/// Cells of the same non-dead state in a row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RleRun {
+    pub row: usize,
+    pub col: usize,
+    pub len: usize,
+    /// 1 for `o`, or 1 to 255 for multi-state letters
+    pub state: u8,
+}
+
+#[derive(Debug)]
+pub enum RleReadError {
+    Io(io::Error),
+    /// Malformed input, at a line and column counted from 1
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// More cells than allowed by [`RleReader::with_max_cells`]
+    TooLarge {
+        width: usize,
+        height: usize,
+    },
+}
+
+impl std::error::Error for RleReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RleReadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RleReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RleReadError::Io(e) => write!(f, "{e}"),
+            RleReadError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "{message} at line {line}, column {column}"),
+            RleReadError::TooLarge { width, height } => {
+                write!(f, "{width}x{height} pattern is too large")
+            }
+        }
+    }
+}
+
+impl From<io::Error> for RleReadError {
+    fn from(e: io::Error) -> Self {
+        RleReadError::Io(e)
+    }
+}
+
+/// Iterator over the runs of live cells of an RLE file.
+///
+/// Accepts the same input as [`parse_rle_states`](super::parse_rle_states),
+/// with the same errors at the same positions, except that the size is only
+/// limited by [`with_max_cells`](Self::with_max_cells). Stops after the first
+/// error.
+#[derive(Debug)]
+pub struct RleReader<R> {
+    reader: R,
+    header: RleHeader,
+    /// Current line of input
+    line: String,
+    /// Number of the current line, counted from 1
+    line_number: usize,
+    /// Byte offset of the next character of the line
+    pos: usize,
+    row: usize,
+    col: usize,
+    /// Run count read before its tag
+    count: Option<usize>,
+    /// Column the current run started at, including its count
+    run_column: usize,
+    done: bool,
+}
+
+impl<R: BufRead> RleReader<R> {
+    /// Reads the comments and header.
+    pub fn new(reader: R) -> Result<Self, RleReadError> {
+        Self::with_max_cells(reader, u64::MAX)
+    }
+
+    /// Reads the comments and header, failing if the pattern's width times
+    /// height is over `max_cells`.
+    pub fn with_max_cells(mut reader: R, max_cells: u64) -> Result<Self, RleReadError> {
+        let mut line = String::new();
+        let mut line_number = 0;
+        let mut comments = Vec::new();
+        let header = loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(syntax_error(line_number + 1, 1, "missing header"));
+            }
+            line_number += 1;
+            if let Some(comment) = line.strip_prefix('#') {
+                comments.push(comment.trim_end().to_owned());
+                continue;
+            }
+            let (_, (width, height, rule)) = header(&line).map_err(|e| {
+                let at = match e {
+                    Err::Error(e) | Err::Failure(e) => e.errors.first().map(|&(at, _)| at),
+                    Err::Incomplete(_) => None,
+                };
+                let column = line.len() - at.map_or(line.len(), str::len) + 1;
+                syntax_error(line_number, column, "invalid header")
+            })?;
+            break RleHeader {
+                width,
+                height,
+                rule: rule.map(str::to_owned),
+                comments,
+            };
+        };
+        if (header.width as u64).saturating_mul(header.height as u64) > max_cells {
+            return Err(RleReadError::TooLarge {
+                width: header.width,
+                height: header.height,
+            });
+        }
+
+        line.clear();
+        Ok(RleReader {
+            reader,
+            header,
+            line,
+            line_number,
+            pos: 0,
+            row: 0,
+            col: 0,
+            count: None,
+            run_column: 0,
+            done: false,
+        })
+    }
+
+    pub fn header(&self) -> &RleHeader {
+        &self.header
+    }
+
+    /// Positions of cells in odd states as `(row, col)`, which are the alive
+    /// cells of two-state and LifeHistory patterns.
+    pub fn alive_cells(mut self) -> impl Iterator<Item = Result<(usize, usize), RleReadError>> {
+        let mut run: Option<RleRun> = None;
+        std::iter::from_fn(move || loop {
+            if let Some(run) = run.as_mut().filter(|run| run.len > 0) {
+                let cell = (run.row, run.col);
+                run.col += 1;
+                run.len -= 1;
+                return Some(Ok(cell));
+            }
+            match self.next()? {
+                Ok(next) if next.state % 2 == 1 => run = Some(next),
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        })
+    }
+
+    fn next_run(&mut self) -> Result<Option<RleRun>, RleReadError> {
+        loop {
+            let Some(c) = self.line[self.pos..].chars().next() else {
+                // the end of input, just past the last character
+                let end = if self.line.is_empty() || self.line.ends_with('\n') {
+                    (self.line_number + 1, 1)
+                } else {
+                    (self.line_number, self.line.len() + 1)
+                };
+                self.line.clear();
+                self.pos = 0;
+                if self.reader.read_line(&mut self.line)? == 0 {
+                    return Err(syntax_error(end.0, end.1, errors::MISSING_END));
+                }
+                self.line_number += 1;
+                continue;
+            };
+            let column = self.pos + 1;
+            self.pos += c.len_utf8();
+            if self.count.is_none() {
+                self.run_column = column;
+            }
+
+            let state = match c {
+                '0'..='9' => {
+                    let digit = c as usize - '0' as usize;
+                    let count = self
+                        .count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(digit));
+                    let error = || self.error(self.run_column, errors::COUNT_TOO_LARGE);
+                    self.count = Some(count.ok_or_else(error)?);
+                    continue;
+                }
+                // counts are directly followed by their tag
+                c if c.is_whitespace() && self.count.is_none() => continue,
+                '!' if self.count.is_some() => {
+                    return Err(self.error(column, errors::COUNT_WITHOUT_TAG))
+                }
+                '!' => return Ok(None),
+                tags::EOL => {
+                    self.row = self.advance(self.row)?;
+                    self.col = 0;
+                    continue;
+                }
+                tags::DEAD | tags::STATE_0 => 0,
+                tags::ALIVE => 1,
+                tags::STATE_1..=tags::STATE_LAST => multi_state(None, c).unwrap(),
+                c if tags::PREFIXES.contains(&c) => {
+                    let letter = self.line[self.pos..].chars().next();
+                    let state = letter.and_then(|letter| multi_state(Some(c), letter));
+                    let Some(state) = state else {
+                        return Err(self.error(column, errors::UNKNOWN_TAG));
+                    };
+                    self.pos += 1;
+                    state
+                }
+                _ => return Err(self.error(column, errors::UNKNOWN_TAG)),
+            };
+            let len = self.count.unwrap_or(1);
+            let col = self.col;
+            self.col = self.advance(col)?;
+            if state == 0 {
+                continue;
+            }
+            if self.col > self.header.width {
+                return Err(self.error(self.run_column, errors::ROW_TOO_WIDE));
+            } else if self.row >= self.header.height {
+                return Err(self.error(self.run_column, errors::TOO_MANY_ROWS));
+            }
+            return Ok(Some(RleRun {
+                row: self.row,
+                col,
+                len,
+                state,
+            }));
+        }
+    }
+
+    /// Adds the run count to a row or column, taking the count.
+    fn advance(&mut self, position: usize) -> Result<usize, RleReadError> {
+        let count = self.count.take().unwrap_or(1);
+        position
+            .checked_add(count)
+            .ok_or_else(|| self.error(self.run_column, errors::COUNT_TOO_LARGE))
+    }
+
+    fn error(&self, column: usize, message: &str) -> RleReadError {
+        syntax_error(self.line_number, column, message)
+    }
+}
+
+impl<R: BufRead> Iterator for RleReader<R> {
+    type Item = Result<RleRun, RleReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.next_run().transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
+fn syntax_error(line: usize, column: usize, message: &str) -> RleReadError {
+    RleReadError::Syntax {
+        line,
+        column,
+        message: message.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse::rle::MALFORMED_CELLS;
+    use crate::{parse_rle, Cell};
+
+    #[test]
+    fn test_runs() {
+        let input = "#N Glider\n#C c/4\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+        let reader = RleReader::new(input.as_bytes()).unwrap();
+        assert_eq!(
+            &RleHeader {
+                width: 3,
+                height: 3,
+                rule: Some("B3/S23".to_owned()),
+                comments: vec!["N Glider".to_owned(), "C c/4".to_owned()],
+            },
+            reader.header()
+        );
+        let runs: Vec<_> = reader.map(Result::unwrap).collect();
+        let run = |row, col, len| RleRun {
+            row,
+            col,
+            len,
+            state: 1,
+        };
+        assert_eq!(vec![run(0, 1, 1), run(1, 2, 1), run(2, 0, 3)], runs);
+    }
+
+    #[test]
+    fn test_matches_parse_rle() {
+        let input = include_pattern!("p43gliderloop.rle");
+        let grid = parse_rle(input).unwrap();
+        let reader = RleReader::new(input.as_bytes()).unwrap();
+        let mut alive: Vec<_> = reader.alive_cells().map(Result::unwrap).collect();
+        alive.sort();
+        let expected: Vec<_> = (0..grid.height)
+            .flat_map(|y| (0..grid.width).map(move |x| (y, x)))
+            .filter(|&(y, x)| grid[(x, y)] == Cell::Alive)
+            .collect();
+        assert_eq!(expected, alive);
+    }
+
+    #[test]
+    fn test_sparse_and_multi_state() {
+        // a billion by a billion cells, split across lines
+        let input = "x = 1000000000, y = 1000000000, rule = LifeHistory:T0,0\n\
+            999999999$\n999999994b2A.CpAyO\n!";
+        let reader = RleReader::new(input.as_bytes()).unwrap();
+        assert_eq!(Some("LifeHistory:T0,0"), reader.header().rule.as_deref());
+        let runs: Vec<_> = reader.map(Result::unwrap).collect();
+        assert_eq!(
+            vec![
+                RleRun {
+                    row: 999_999_999,
+                    col: 999_999_994,
+                    len: 2,
+                    state: 1
+                },
+                RleRun {
+                    row: 999_999_999,
+                    col: 999_999_997,
+                    len: 1,
+                    state: 3
+                },
+                RleRun {
+                    row: 999_999_999,
+                    col: 999_999_998,
+                    len: 1,
+                    state: 25
+                },
+                RleRun {
+                    row: 999_999_999,
+                    col: 999_999_999,
+                    len: 1,
+                    state: 255
+                },
+            ],
+            runs
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let big = "x = 100000, y = 100000\n!";
+        assert!(matches!(
+            RleReader::with_max_cells(big.as_bytes(), 1 << 20),
+            Err(RleReadError::TooLarge {
+                width: 100000,
+                height: 100000
+            })
+        ));
+
+        assert_eq!("missing header at line 2, column 1", errors("#C only\n"));
+        assert_eq!("invalid header at line 1, column 6", errors("x = 3\n!"));
+        assert_eq!(
+            "invalid header at line 1, column 8",
+            errors("x = 3, z = 3\n!")
+        );
+        assert_eq!(
+            "missing `!` at the end of the cells at line 2, column 4",
+            errors("x = 3, y = 3\n3o$")
+        );
+        // counts can't be split from their tag
+        assert_eq!(
+            "unknown tag at line 2, column 3",
+            errors("x = 3, y = 3\n99\n9b!")
+        );
+    }
+
+    #[test]
+    fn test_matches_parse_rle_errors() {
+        for (input, message, _) in MALFORMED_CELLS {
+            // the innermost error's message, line and caret column
+            let expected = parse_rle(input).unwrap_err().to_string();
+            let mut lines = expected.lines().skip(1);
+            let (line, context) = lines.next().unwrap()[..].split_once(", in ").unwrap();
+            assert_eq!(format!("{message}:"), context);
+            let line = line.trim_start_matches("0: at line ");
+            let column = lines.nth(1).unwrap().find('^').unwrap() + 1;
+            assert_eq!(
+                format!("{message} at line {line}, column {column}"),
+                errors(input),
+                "{input:?}"
+            );
+        }
+    }
+
+    fn errors(input: &str) -> String {
+        let reader = RleReader::new(input.as_bytes());
+        match reader.map(|reader| reader.collect::<Result<Vec<_>, _>>()) {
+            Ok(Ok(runs)) => panic!("read {runs:?}"),
+            Ok(Err(e)) | Err(e) => e.to_string(),
+        }
+    }
+}
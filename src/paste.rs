@@ -0,0 +1,161 @@
+//! Compositing patterns into an existing universe
+
+#[cfg(feature = "wasm")]
+use js_sys::TypeError;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{prelude::*, JsValue};
+
+use crate::{Cell, EdgeBehavior, Grid, Universe};
+
+/// How pasted cells combine with the cells already in the universe.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasteMode {
+    /// Replace the cells under the pattern
+    Overwrite,
+    /// Cells alive in either the pattern or the universe
+    Or,
+    /// Cells alive in exactly one of the pattern or the universe
+    Xor,
+    /// Cells alive in both the pattern and the universe
+    And,
+}
+
+impl PasteMode {
+    fn combine(self, existing: Cell, pasted: Cell) -> Cell {
+        let alive = match self {
+            PasteMode::Overwrite => pasted == Cell::Alive,
+            PasteMode::Or => existing == Cell::Alive || pasted == Cell::Alive,
+            PasteMode::Xor => (existing == Cell::Alive) != (pasted == Cell::Alive),
+            PasteMode::And => existing == Cell::Alive && pasted == Cell::Alive,
+        };
+        if alive {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+}
+
+/// Pasting methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Parses a pattern file and pastes it with its top left corner at
+    /// `row`, `col`.
+    ///
+    /// See [`paste`](Self::paste).
+    #[cfg(feature = "wasm")]
+    pub fn paste_file(
+        &mut self,
+        f: &[u8],
+        row: i32,
+        col: i32,
+        mode: PasteMode,
+    ) -> Result<(), JsValue> {
+        let grid = Self::grid_of_file(f).map_err(|e| TypeError::new(e.to_string().as_ref()))?;
+        self.paste(&grid, row, col, mode);
+        Ok(())
+    }
+}
+
+impl Universe {
+    /// Pastes a grid with its top left corner at `row`, `col`.
+    ///
+    /// Parts of the grid past the edges wrap around when the edge behavior is
+    /// [`EdgeBehavior::Wrap`], and are dropped otherwise. Negative offsets
+    /// place the corner above or left of the universe.
+    ///
+    /// Changed cells are written to the delta buffers and recorded as a single
+    /// edit in the history.
+    pub fn paste(&mut self, grid: &Grid, row: i32, col: i32, mode: PasteMode) {
+        let mut changes = Vec::new();
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let (r, c) = (row as i64 + y as i64, col as i64 + x as i64);
+                if let Some(idx) = self.edge_index(r, c) {
+                    changes.push((idx, mode.combine(self.cells[idx], grid[(x, y)])));
+                }
+            }
+        }
+        self.edit_cells(changes);
+    }
+
+    /// Index of a cell that may be past the edges.
+    ///
+    /// Wraps around when the edge behavior is [`EdgeBehavior::Wrap`], and is
+    /// `None` for cells outside of the universe otherwise.
+    pub(crate) fn edge_index(&self, row: i64, col: i64) -> Option<usize> {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let (row, col) = if self.edge_behavior == EdgeBehavior::Wrap {
+            (row.rem_euclid(height), col.rem_euclid(width))
+        } else if (0..height).contains(&row) && (0..width).contains(&col) {
+            (row, col)
+        } else {
+            return None;
+        };
+        Some(self.get_index(row as u32, col as u32))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn grid(s: &str) -> Grid {
+        crate::parse_plaintext(s).unwrap()
+    }
+
+    #[test]
+    fn test_paste_modes() {
+        let pattern = grid("!\nOO\n..\n");
+        for (mode, expected) in [
+            (PasteMode::Overwrite, "OO\n..\n"),
+            (PasteMode::Or, "OO\nO.\n"),
+            (PasteMode::Xor, ".O\nO.\n"),
+            (PasteMode::And, "O.\n..\n"),
+        ] {
+            let mut universe = Universe::new(2, 2);
+            universe.set_cells(&[(0, 0), (1, 0)]);
+            universe.paste(&pattern, 0, 0, mode);
+            assert_eq!(
+                expected,
+                universe.region(0, 0, 2, 2).to_string(),
+                "{mode:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_paste_wraps() {
+        let mut universe = Universe::new(4, 4);
+        universe.paste(&grid("!\nOO\nOO\n"), 3, -1, PasteMode::Or);
+        assert_eq!(
+            "O..O\n....\n....\nO..O\n",
+            universe.region(0, 0, 4, 4).to_string()
+        );
+    }
+
+    #[test]
+    fn test_paste_clips() {
+        let mut universe = Universe::new(4, 4);
+        universe.set_edge_behavior(EdgeBehavior::Dead);
+        universe.paste(&grid("!\nOO\nOO\n"), 3, -1, PasteMode::Or);
+        assert_eq!(
+            "....\n....\n....\nO...\n",
+            universe.region(0, 0, 4, 4).to_string()
+        );
+    }
+
+    #[test]
+    fn test_paste_deltas_and_undo() {
+        let mut universe = Universe::new(4, 4);
+        universe.set_history_budget(1 << 20);
+        universe.set_cells(&[(0, 0)]);
+        universe.paste(&grid("!\n.O\n"), 0, 0, PasteMode::Overwrite);
+        assert_eq!(1, universe.cells_born_count());
+        assert_eq!(1, universe.cells_died_count());
+
+        assert!(universe.undo());
+        assert_eq!("O.\n", universe.region(0, 0, 2, 1).to_string());
+    }
+}
@@ -0,0 +1,108 @@
+//! Detection of still lifes, oscillators and spaceships
+//!
+//! A pattern is periodic once its alive cells repeat, ignoring where they are.
+//! Patterns that moved in between are spaceships.
+
+use std::collections::HashMap;
+
+use crate::{Cell, Grid, Universe};
+
+/// How a pattern repeats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Period {
+    /// First generation of the cycle, counted from the starting generation
+    pub start: u32,
+    /// Generations per cycle, 1 for still lifes
+    pub period: u32,
+    /// Cells moved per cycle as `(rows, cols)`, zero unless a spaceship
+    pub offset: (i64, i64),
+    /// Alive cells at the start of the cycle, 0 if the pattern died out
+    pub population: u32,
+}
+
+impl Period {
+    pub fn is_spaceship(&self) -> bool {
+        self.offset != (0, 0)
+    }
+}
+
+impl Universe {
+    /// Advances a copy of the universe until its pattern repeats.
+    ///
+    /// Returns `None` if it has not repeated after `max_generations`.
+    pub fn find_period(&self, max_generations: u32) -> Option<Period> {
+        let mut universe = self.copy_state();
+
+        let mut seen = HashMap::new();
+        for generation in 0..=max_generations {
+            let (pattern, origin) = universe.pattern();
+            let population = pattern.cells.iter().filter(|&&c| c == Cell::Alive).count();
+            if let Some((start, start_origin)) = seen.insert(pattern, (generation, origin)) {
+                return Some(Period {
+                    start,
+                    period: generation - start,
+                    offset: (origin.0 - start_origin.0, origin.1 - start_origin.1),
+                    population: population as u32,
+                });
+            }
+            universe.tick();
+        }
+        None
+    }
+
+    /// The alive cells cropped to their bounding box, and the box's top left
+    /// corner as `(row, col)`.
+    fn pattern(&self) -> (Grid, (i64, i64)) {
+        let grid = self.region(0, 0, self.width, self.height);
+        match grid.bounding_box() {
+            Some((x, y, w, h)) => (
+                self.region(y as u32, x as u32, w as u32, h as u32),
+                (y as i64, x as i64),
+            ),
+            None => (Grid::new(0, 0), (0, 0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::EdgeBehavior;
+
+    #[test]
+    fn test_still_life_and_oscillator() {
+        let mut block = Universe::new(6, 6);
+        block.set_cells(&[(1, 1), (1, 2), (2, 1), (2, 2)]);
+        let period = block.find_period(10).unwrap();
+        assert_eq!((0, 1, (0, 0)), (period.start, period.period, period.offset));
+        assert_eq!(4, period.population);
+
+        let mut blinker = Universe::new(5, 5);
+        blinker.set_cells(&[(2, 1), (2, 2), (2, 3)]);
+        assert_eq!(2, blinker.find_period(10).unwrap().period);
+    }
+
+    #[test]
+    fn test_spaceship() {
+        let mut glider = Universe::new(16, 16);
+        glider.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        glider.set_edge_behavior(EdgeBehavior::Dead);
+        let period = glider.find_period(10).unwrap();
+        assert_eq!(4, period.period);
+        assert_eq!((1, 1), period.offset);
+        assert!(period.is_spaceship());
+    }
+
+    #[test]
+    fn test_not_found_and_dying() {
+        // an R-pentomino takes over a thousand generations to settle
+        let mut r = Universe::new(64, 64);
+        r.set_cells(&[(30, 31), (30, 32), (31, 30), (31, 31), (32, 31)]);
+        assert_eq!(None, r.find_period(20));
+
+        let mut single = Universe::new(4, 4);
+        single.set_cells(&[(1, 1)]);
+        let period = single.find_period(5).unwrap();
+        assert_eq!((1, 1, 0), (period.start, period.period, period.population));
+    }
+}
@@ -0,0 +1,252 @@
+//! Seeded random soup generation
+//!
+//! Soups are generated with a small deterministic PRNG seeded from a string, so
+//! the same seed, density and symmetry always produce the same soup on every
+//! target. Symmetries follow the naming used by apgsearch.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Grid, PasteMode, Symmetry, Universe};
+
+/// Symmetry imposed on a random soup.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoupSymmetry {
+    /// No symmetry
+    C1,
+    /// Invariant under 180° rotation
+    C2,
+    /// Invariant under 90° rotation
+    C4,
+    /// Mirrored left to right
+    D2,
+    /// Mirrored left to right and top to bottom
+    D4,
+    /// Invariant under all rotations and reflections
+    D8,
+}
+
+impl SoupSymmetry {
+    fn group(self) -> &'static [Symmetry] {
+        use Symmetry::*;
+        match self {
+            SoupSymmetry::C1 => &[Identity],
+            SoupSymmetry::C2 => &[Identity, Rotate180],
+            SoupSymmetry::C4 => &[Identity, Rotate90, Rotate180, Rotate270],
+            SoupSymmetry::D2 => &[Identity, FlipHorizontal],
+            SoupSymmetry::D4 => &[Identity, FlipHorizontal, FlipVertical, Rotate180],
+            SoupSymmetry::D8 => &Symmetry::ALL,
+        }
+    }
+
+    /// Whether the symmetry needs a square area.
+    fn is_square(self) -> bool {
+        self.group().iter().any(|s| s.swaps_axes())
+    }
+}
+
+/// SplitMix64 pseudo-random number generator.
+///
+/// Not suitable for cryptography, but fast, tiny and identical on every
+/// platform.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Seeds the generator with the FNV-1a hash of a string.
+    pub fn from_seed_str(seed: &str) -> Self {
+        let hash = seed.bytes().fold(0xcbf29ce484222325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        Self::new(hash)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A cell that is alive with probability `density`.
+    pub fn next_cell(&mut self, density: f64) -> Cell {
+        if self.next_f64() < density {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+}
+
+impl Grid {
+    /// Generates a random soup from a seed string.
+    ///
+    /// Each cell is alive with probability `density`. Symmetries that include
+    /// 90° rotations or diagonal reflections ([`SoupSymmetry::C4`] and
+    /// [`SoupSymmetry::D8`]) fill the largest square in the top left corner
+    /// and leave the rest dead.
+    pub fn random_soup(
+        width: usize,
+        height: usize,
+        seed: &str,
+        density: f64,
+        symmetry: SoupSymmetry,
+    ) -> Grid {
+        let mut rng = Rng::from_seed_str(seed);
+        let mut grid = Grid::new(width, height);
+        let (w, h) = if symmetry.is_square() {
+            (width.min(height), width.min(height))
+        } else {
+            (width, height)
+        };
+        let mut drawn = vec![false; w * h];
+
+        for y in 0..h {
+            for x in 0..w {
+                if drawn[y * w + x] {
+                    continue;
+                }
+                let cell = rng.next_cell(density);
+                for transform in symmetry.group() {
+                    let (tx, ty) = transform.map((x, y), w, h);
+                    drawn[ty * w + tx] = true;
+                    grid[(tx, ty)] = cell;
+                }
+            }
+        }
+        grid
+    }
+}
+
+/// Random generation methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Fills the universe with a random soup, returning the seed used.
+    ///
+    /// Half of the cells are alive. Pass the seed to
+    /// [`reset_random_seeded`](Self::reset_random_seeded) to recreate the soup.
+    pub fn reset_random(&mut self) -> String {
+        let seed = format!("{:016x}", Rng::new(entropy()).next_u64());
+        self.reset_random_seeded(&seed, 0.5);
+        seed
+    }
+
+    /// Fills the universe with a random soup generated from `seed`.
+    pub fn reset_random_seeded(&mut self, seed: &str, density: f64) {
+        let mut rng = Rng::from_seed_str(seed);
+        self.reset_with(|_i| rng.next_cell(density));
+    }
+
+    /// Overwrites a region with a random soup generated from `seed`.
+    ///
+    /// See [`Grid::random_soup`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn fill_random(
+        &mut self,
+        row: u32,
+        col: u32,
+        width: u32,
+        height: u32,
+        seed: &str,
+        density: f64,
+        symmetry: SoupSymmetry,
+    ) {
+        let soup = Grid::random_soup(width as usize, height as usize, seed, density, symmetry);
+        self.paste(&soup, row as i32, col as i32, PasteMode::Overwrite);
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn entropy() -> u64 {
+    (js_sys::Math::random() * u64::MAX as f64) as u64
+}
+
+#[cfg(not(feature = "wasm"))]
+fn entropy() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rng_reproducible() {
+        let mut a = Rng::from_seed_str("soup");
+        let mut b = Rng::from_seed_str("soup");
+        let mut c = Rng::from_seed_str("soup2");
+        let a: Vec<_> = (0..8).map(|_| a.next_u64()).collect();
+        let b: Vec<_> = (0..8).map(|_| b.next_u64()).collect();
+        let c: Vec<_> = (0..8).map(|_| c.next_u64()).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_density() {
+        let soup = Grid::random_soup(100, 100, "density", 0.25, SoupSymmetry::C1);
+        let alive = soup.cells.iter().filter(|&&c| c == Cell::Alive).count();
+        assert!((2000..3000).contains(&alive), "{alive}");
+
+        let empty = Grid::random_soup(10, 10, "density", 0.0, SoupSymmetry::C1);
+        assert!(empty.cells.iter().all(|&c| c == Cell::Dead));
+    }
+
+    #[test]
+    fn test_symmetries() {
+        for symmetry in [
+            SoupSymmetry::C1,
+            SoupSymmetry::C2,
+            SoupSymmetry::C4,
+            SoupSymmetry::D2,
+            SoupSymmetry::D4,
+            SoupSymmetry::D8,
+        ] {
+            let soup = Grid::random_soup(16, 16, "symmetric", 0.5, symmetry);
+            assert_eq!(
+                soup,
+                Grid::random_soup(16, 16, "symmetric", 0.5, symmetry),
+                "{symmetry:?}"
+            );
+            for &transform in symmetry.group() {
+                assert_eq!(
+                    soup,
+                    soup.transform(transform),
+                    "{symmetry:?} {transform:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_square_symmetry_in_rectangle() {
+        let soup = Grid::random_soup(6, 4, "wide", 1.0, SoupSymmetry::C4);
+        assert_eq!("OOOO..\nOOOO..\nOOOO..\nOOOO..\n", soup.to_string());
+    }
+
+    #[test]
+    fn test_reset_random_seed() {
+        let mut universe = Universe::new(8, 8);
+        let seed = universe.reset_random();
+        let cells = universe.get_cells().to_vec();
+        universe.reset_blank();
+        universe.reset_random_seeded(&seed, 0.5);
+        assert_eq!(cells, universe.get_cells());
+    }
+}
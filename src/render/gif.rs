@@ -0,0 +1,394 @@
+//! Animated GIF recording of simulations
+//!
+//! Frames are rendered with a [`Framebuffer`]'s options and share one global
+//! color table built from the colors drawn. Past 256 colors, which only the
+//! age and heat color modes can reach, colors are mapped to the nearest one
+//! already in the table.
+
+#[cfg(feature = "wasm")]
+use js_sys::TypeError;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{prelude::*, JsValue};
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use super::{Color, Framebuffer};
+use crate::Universe;
+
+const MAX_COLORS: usize = 256;
+const MAX_CODE: u16 = 4096;
+
+/// Graphic control extension disposal method: leave the frame in place
+const DISPOSAL_NONE: u8 = 1 << 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GifOptions {
+    /// Number of frames, the first being the current generation
+    pub frames: u32,
+    /// Time each frame is shown, in hundredths of a second
+    pub frame_delay: u16,
+    /// Region recorded as `(row, col, width, height)`, or all of the universe
+    pub crop: Option<(u32, u32, u32, u32)>,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        GifOptions {
+            frames: 60,
+            frame_delay: 10,
+            crop: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GifError {
+    /// No frames, or a region without cells
+    Empty,
+    /// Frames larger than 65535 pixels in either dimension
+    TooLarge { width: u32, height: u32 },
+}
+
+impl std::error::Error for GifError {}
+
+impl Display for GifError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GifError::Empty => write!(f, "nothing to record"),
+            GifError::TooLarge { width, height } => {
+                write!(f, "{width}x{height} pixel frames are too large for a GIF")
+            }
+        }
+    }
+}
+
+/// GIF methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Framebuffer {
+    /// Records `frames` generations of the universe, cropped to its selection
+    /// if there is one, as a looping animated GIF.
+    ///
+    /// Neither the universe nor this buffer are changed.
+    #[cfg(feature = "wasm")]
+    pub fn to_gif(
+        &self,
+        universe: &Universe,
+        frames: u32,
+        frame_delay: u16,
+    ) -> Result<Vec<u8>, JsValue> {
+        let crop = universe
+            .get_selection()
+            .map(|s| (s.row, s.col, s.width, s.height));
+        let options = GifOptions {
+            frames,
+            frame_delay,
+            crop,
+        };
+        self.record_gif(universe, &options)
+            .map_err(|e| TypeError::new(e.to_string().as_ref()).into())
+    }
+}
+
+impl Framebuffer {
+    /// Records generations of a copy of the universe as a looping animated
+    /// GIF, drawn with this buffer's options.
+    pub fn record_gif(
+        &self,
+        universe: &Universe,
+        options: &GifOptions,
+    ) -> Result<Vec<u8>, GifError> {
+        let (row, col, width, height) =
+            options
+                .crop
+                .unwrap_or((0, 0, universe.width(), universe.height()));
+        let mut universe = universe.copy_state();
+        let mut framebuffer = Framebuffer::with_options(*self.options());
+        let mut table = ColorTable::default();
+        let mut frames = Vec::new();
+        for i in 0..options.frames {
+            if i > 0 {
+                universe.tick();
+            }
+            framebuffer.draw_region(&universe, row, col, width, height);
+            let indices: Vec<u8> = framebuffer
+                .as_bytes()
+                .chunks_exact(4)
+                .map(|pixel| table.index(pixel.try_into().unwrap()))
+                .collect();
+            frames.push(indices);
+        }
+
+        let (width, height) = (framebuffer.width(), framebuffer.height());
+        if frames.is_empty() || width == 0 || height == 0 {
+            return Err(GifError::Empty);
+        }
+        let too_large = GifError::TooLarge { width, height };
+        let width = u16::try_from(width).map_err(|_| too_large.clone())?;
+        let height = u16::try_from(height).map_err(|_| too_large)?;
+        Ok(encode_gif(
+            width,
+            height,
+            &table.colors,
+            &frames,
+            options.frame_delay,
+        ))
+    }
+}
+
+/// Colors of a GIF's global color table.
+#[derive(Default)]
+struct ColorTable {
+    colors: Vec<Color>,
+    indices: HashMap<Color, u8>,
+}
+
+impl ColorTable {
+    /// Index of a color, adding it to the table if there is room, or else
+    /// the index of the nearest color.
+    fn index(&mut self, color: Color) -> u8 {
+        if let Some(&index) = self.indices.get(&color) {
+            return index;
+        }
+        let index = if self.colors.len() < MAX_COLORS {
+            self.colors.push(color);
+            (self.colors.len() - 1) as u8
+        } else {
+            let distance = |c: &Color| {
+                (0..3)
+                    .map(|i| (c[i] as i32 - color[i] as i32).pow(2))
+                    .sum::<i32>()
+            };
+            let nearest = (0..self.colors.len()).min_by_key(|&i| distance(&self.colors[i]));
+            nearest.unwrap() as u8
+        };
+        self.indices.insert(color, index);
+        index
+    }
+}
+
+/// Encodes frames of color table indices as a looping GIF.
+fn encode_gif(
+    width: u16,
+    height: u16,
+    colors: &[Color],
+    frames: &[Vec<u8>],
+    delay: u16,
+) -> Vec<u8> {
+    // color tables have 2^(n + 1) entries
+    let table_bits = (colors.len().max(2).next_power_of_two().trailing_zeros() as u8).max(1);
+    let min_code_size = table_bits.max(2);
+
+    let mut out = b"GIF89a".to_vec();
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    // global color table, 8 bits per primary
+    out.push(0x80 | 0x70 | (table_bits - 1));
+    // background color and pixel aspect ratio
+    out.extend_from_slice(&[0, 0]);
+    for i in 0..1 << table_bits {
+        let [r, g, b, _] = colors.get(i).copied().unwrap_or_default();
+        out.extend_from_slice(&[r, g, b]);
+    }
+
+    // loop forever
+    out.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+    out.extend_from_slice(b"NETSCAPE2.0");
+    out.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    for frame in frames {
+        // graphic control extension
+        out.extend_from_slice(&[0x21, 0xF9, 0x04, DISPOSAL_NONE]);
+        out.extend_from_slice(&delay.to_le_bytes());
+        out.extend_from_slice(&[0x00, 0x00]);
+
+        // image descriptor, at 0, 0 without a local color table
+        out.push(0x2C);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        out.push(0);
+
+        out.push(min_code_size);
+        for block in lzw_encode(frame, min_code_size).chunks(255) {
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+    }
+    out.push(0x3B);
+    out
+}
+
+/// Compresses color table indices with GIF's variable code size LZW.
+fn lzw_encode(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut codes: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut code_size = min_code_size + 1;
+    let mut bits = BitWriter::default();
+
+    bits.write(clear, code_size);
+    let Some((&first, rest)) = indices.split_first() else {
+        bits.write(end, code_size);
+        return bits.finish();
+    };
+    let mut prefix = first as u16;
+    for &index in rest {
+        if let Some(&code) = codes.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        bits.write(prefix, code_size);
+        if next_code == MAX_CODE {
+            // the table is full, start over
+            bits.write(clear, code_size);
+            codes.clear();
+            next_code = end + 1;
+            code_size = min_code_size + 1;
+        } else {
+            if next_code >= 1 << code_size {
+                code_size += 1;
+            }
+            codes.insert((prefix, index), next_code);
+            next_code += 1;
+        }
+        prefix = index as u16;
+    }
+    bits.write(prefix, code_size);
+    bits.write(end, code_size);
+    bits.finish()
+}
+
+/// Packs codes least significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u32,
+    len: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.len;
+        self.len += size;
+        while self.len >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ColorMode, EdgeBehavior, RenderOptions};
+
+    /// Frames as `(width, height, delay, RGBA pixels)`
+    fn decode(bytes: &[u8]) -> Vec<(u16, u16, u16, Vec<u8>)> {
+        let mut options = ::gif::DecodeOptions::new();
+        options.set_color_output(::gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push((
+                frame.width,
+                frame.height,
+                frame.delay,
+                frame.buffer.to_vec(),
+            ));
+        }
+        frames
+    }
+
+    fn glider() -> Universe {
+        let mut universe = Universe::new(8, 8);
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        universe
+    }
+
+    #[test]
+    fn test_lzw_table_reset() {
+        // noisy enough to fill the code table and clear it
+        let indices: Vec<u8> = (0..40_000u32).map(|i| (i * i % 251) as u8).collect();
+        let colors: Vec<Color> = (0..=255).map(|i| [i, i, i, 0xFF]).collect();
+        let gif = encode_gif(200, 200, &colors, std::slice::from_ref(&indices), 0);
+        let frames = decode(&gif);
+        let grays: Vec<u8> = frames[0].3.chunks_exact(4).map(|p| p[0]).collect();
+        assert_eq!(indices, grays);
+    }
+
+    #[test]
+    fn test_record_gif() {
+        let universe = glider();
+        let framebuffer = Framebuffer::new(2, true);
+        let options = GifOptions {
+            frames: 5,
+            frame_delay: 7,
+            crop: None,
+        };
+        let gif = framebuffer.record_gif(&universe, &options).unwrap();
+        let frames = decode(&gif);
+        assert_eq!(5, frames.len());
+
+        // each frame matches drawing that generation
+        let mut expected = glider();
+        let mut reference = Framebuffer::new(2, true);
+        for (width, height, delay, pixels) in frames {
+            reference.draw(&expected);
+            assert_eq!((25, 25, 7), (width, height, delay));
+            assert_eq!(reference.as_bytes(), pixels);
+            expected.tick();
+        }
+        // the universe is unchanged
+        assert_eq!(0, universe.generation());
+    }
+
+    #[test]
+    fn test_crop_and_empty() {
+        let mut universe = glider();
+        universe.set_edge_behavior(EdgeBehavior::Dead);
+        universe.set_track_activity(true);
+        let framebuffer = Framebuffer::with_options(RenderOptions {
+            cell_size: 1,
+            grid_lines: false,
+            color_mode: ColorMode::Heat,
+            ..Default::default()
+        });
+        let options = GifOptions {
+            frames: 3,
+            crop: Some((0, 0, 4, 3)),
+            ..Default::default()
+        };
+        let frames = decode(&framebuffer.record_gif(&universe, &options).unwrap());
+        assert_eq!(3, frames.len());
+        assert!(frames.iter().all(|&(w, h, _, _)| (w, h) == (4, 3)));
+
+        let empty = GifOptions {
+            frames: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            Err(GifError::Empty),
+            framebuffer.record_gif(&universe, &empty)
+        );
+    }
+
+    #[test]
+    fn test_color_table_nearest() {
+        let mut table = ColorTable::default();
+        for i in 0..=255 {
+            assert_eq!(i, table.index([i, 0, 0, 0xFF]));
+        }
+        assert_eq!(200, table.index([200, 3, 0, 0xFF]));
+    }
+}
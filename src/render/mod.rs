@@ -0,0 +1,511 @@
+//! Rendering of universes to pixel buffers and text
+//!
+//! [`Framebuffer`] draws cells into an RGBA buffer laid out like the canvas
+//! `ImageData`, so JS can blit a whole frame with a single `putImageData`.
+//! [`Universe::render_text`] draws them as text for terminals.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Grid, Universe, MAX_HEAT, OVERLAY_BOUNDARY, OVERLAY_HISTORY, OVERLAY_MARK};
+
+mod gif;
+pub use gif::*;
+mod png;
+pub use png::*;
+mod svg;
+pub use svg::*;
+mod text;
+pub use text::*;
+mod viewport;
+pub use viewport::*;
+
+/// RGBA color
+pub type Color = [u8; 4];
+
+/// Number of generations over which [`ColorMode::Age`] fades from the newborn
+/// to the alive color.
+const AGE_SPAN: u16 = 64;
+
+/// Colors shared by the renderers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub alive: Color,
+    pub dead: Color,
+    pub grid: Color,
+    /// Alive cells that were just born, for [`ColorMode::Age`]
+    pub newborn: Color,
+    /// Dead cells that just changed, for [`ColorMode::Heat`]
+    pub heat: Color,
+    /// Dead cells that have been alive, when the overlay is enabled
+    pub history: Color,
+    /// Marked alive cells, blended with the dead color for marked dead cells
+    pub mark: Color,
+    /// Boundary cells
+    pub boundary: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            alive: [0x00, 0x00, 0x00, 0xFF],
+            dead: [0xFF, 0xFF, 0xFF, 0xFF],
+            grid: [0xEE, 0xEE, 0xEE, 0xFF],
+            newborn: [0xE0, 0x30, 0x30, 0xFF],
+            heat: [0xFF, 0xA0, 0x40, 0xFF],
+            history: [0xC8, 0xEE, 0xC8, 0xFF],
+            mark: [0x80, 0x30, 0xA0, 0xFF],
+            boundary: [0x90, 0x90, 0x90, 0xFF],
+        }
+    }
+}
+
+impl Palette {
+    fn cell(&self, cell: Cell) -> Color {
+        match cell {
+            Cell::Alive => self.alive,
+            Cell::Dead => self.dead,
+        }
+    }
+}
+
+/// How cells are colored.
+///
+/// [`ColorMode::Age`] and [`ColorMode::Heat`] need activity tracking enabled
+/// on the universe (see [`Universe::set_track_activity`]), and fall back to
+/// [`ColorMode::Plain`] otherwise.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Alive and dead colors only
+    #[default]
+    Plain,
+    /// Alive cells fade from the newborn to the alive color as they age
+    Age,
+    /// Dead cells fade from the heat to the dead color after changing
+    Heat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Width and height of each cell in pixels, not including grid lines
+    pub cell_size: u32,
+    /// Draw 1 pixel lines between cells
+    pub grid_lines: bool,
+    pub palette: Palette,
+    pub color_mode: ColorMode,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            cell_size: 5,
+            grid_lines: true,
+            palette: Palette::default(),
+            color_mode: ColorMode::default(),
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Color of a universe's cell by index, according to the color mode.
+    fn cell_color(&self, universe: &Universe, idx: usize) -> Color {
+        let cell = universe.cells[idx];
+        let palette = &self.palette;
+        if let Some(overlay) = universe.get_overlay() {
+            let flags = overlay[idx];
+            match cell {
+                Cell::Alive if flags & OVERLAY_MARK != 0 => return palette.mark,
+                Cell::Dead if flags & OVERLAY_BOUNDARY != 0 => return palette.boundary,
+                Cell::Dead if flags & OVERLAY_MARK != 0 => {
+                    return blend(palette.dead, palette.mark, 0.3)
+                }
+                Cell::Dead
+                    if flags & OVERLAY_HISTORY != 0 && self.color_mode != ColorMode::Heat =>
+                {
+                    return palette.history
+                }
+                _ => {}
+            }
+        }
+        match (self.color_mode, cell, &universe.activity) {
+            (ColorMode::Age, Cell::Alive, Some(activity)) => {
+                let age = activity.ages[idx].saturating_sub(1).min(AGE_SPAN);
+                blend(palette.newborn, palette.alive, age as f64 / AGE_SPAN as f64)
+            }
+            (ColorMode::Heat, Cell::Dead, Some(activity)) => {
+                let heat = activity.heat[idx] as f64 / MAX_HEAT as f64;
+                blend(palette.dead, palette.heat, heat)
+            }
+            _ => palette.cell(cell),
+        }
+    }
+
+    /// Whether cell colors can change without the cell changing state.
+    fn is_animated(&self) -> bool {
+        self.color_mode != ColorMode::Plain
+    }
+
+    /// Distance in pixels between the top left corners of adjacent cells.
+    fn pitch(&self) -> u32 {
+        self.cell_size + self.grid_lines as u32
+    }
+
+    /// Size in pixels of `cells` cells, including grid lines.
+    fn pixels_for(&self, cells: u32) -> u32 {
+        self.pitch() * cells + self.grid_lines as u32
+    }
+}
+
+/// RGBA pixel buffer of rendered cells.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    options: RenderOptions,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// public methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Framebuffer {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(cell_size: u32, grid_lines: bool) -> Framebuffer {
+        Self::with_options(RenderOptions {
+            cell_size,
+            grid_lines,
+            ..Default::default()
+        })
+    }
+
+    /// Sets colors from `0xRRGGBBAA` values.
+    pub fn set_colors(&mut self, alive: u32, dead: u32, grid: u32) {
+        self.options.palette = Palette {
+            alive: alive.to_be_bytes(),
+            dead: dead.to_be_bytes(),
+            grid: grid.to_be_bytes(),
+            ..self.options.palette
+        };
+        // force a full redraw
+        self.width = 0;
+        self.height = 0;
+    }
+
+    /// Sets how cells are colored, taking effect on the next full draw.
+    pub fn set_color_mode(&mut self, mode: ColorMode) {
+        self.options.color_mode = mode;
+    }
+
+    /// Width of the buffer in pixels.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the buffer in pixels.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns a pointer to the pixel buffer.
+    ///
+    /// Pixels are 4 bytes of RGBA, laid out as a linear stack of rows.
+    pub fn pixels(&self) -> *const u8 {
+        self.pixels.as_ptr()
+    }
+
+    pub fn pixels_len(&self) -> usize {
+        self.pixels.len()
+    }
+
+    /// Redraws every cell of the universe, resizing the buffer if needed.
+    pub fn draw(&mut self, universe: &Universe) {
+        let options = self.options;
+        self.draw_cells(universe.width(), universe.height(), |i| {
+            options.cell_color(universe, i)
+        });
+    }
+
+    /// Draws a `width` by `height` region of cells with its top left corner
+    /// at `row`, `col`, clipped to the universe.
+    pub fn draw_region(
+        &mut self,
+        universe: &Universe,
+        row: u32,
+        col: u32,
+        width: u32,
+        height: u32,
+    ) {
+        let options = self.options;
+        let width = width.min(universe.width().saturating_sub(col));
+        let height = height.min(universe.height().saturating_sub(row));
+        self.draw_cells(width, height, |i| {
+            let (r, c) = (row + i as u32 / width, col + i as u32 % width);
+            options.cell_color(universe, universe.get_index(r, c))
+        });
+    }
+
+    /// Redraws only the cells in the universe's delta buffers.
+    ///
+    /// Falls back to [`draw`](Self::draw) if the buffer does not match the
+    /// universe's dimensions, or if the color mode changes colors of cells
+    /// that did not change state.
+    pub fn draw_delta(&mut self, universe: &Universe) {
+        let (width, height) = self.size_for(universe.width(), universe.height());
+        if (width, height) != (self.width, self.height) || self.options.is_animated() {
+            return self.draw(universe);
+        }
+
+        for delta in [&universe.delta_alive, &universe.delta_dead] {
+            for pair in delta.chunks_exact(2) {
+                let idx = (pair[0] * universe.width() + pair[1]) as usize;
+                let color = self.options.cell_color(universe, idx);
+                self.fill_cell(pair[0], pair[1], color);
+            }
+        }
+    }
+}
+
+impl Framebuffer {
+    pub fn with_options(options: RenderOptions) -> Framebuffer {
+        Framebuffer {
+            options,
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+        }
+    }
+
+    pub fn options(&self) -> &RenderOptions {
+        &self.options
+    }
+
+    /// Renders a grid into a new buffer.
+    pub fn of_grid(grid: &Grid, options: RenderOptions) -> Framebuffer {
+        let mut framebuffer = Self::with_options(options);
+        framebuffer.draw_cells(grid.width as u32, grid.height as u32, |i| {
+            options.palette.cell(grid.cells[i])
+        });
+        framebuffer
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Color of the pixel at `x`, `y`.
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let i = ((y * self.width + x) * 4) as usize;
+        self.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    fn size_for(&self, width: u32, height: u32) -> (u32, u32) {
+        (
+            self.options.pixels_for(width),
+            self.options.pixels_for(height),
+        )
+    }
+
+    /// Redraws a `width` by `height` area of cells, colored by index.
+    fn draw_cells(&mut self, width: u32, height: u32, color: impl Fn(usize) -> Color) {
+        let (px_width, px_height) = self.size_for(width, height);
+        self.width = px_width;
+        self.height = px_height;
+        self.pixels.clear();
+        self.pixels.resize((px_width * px_height * 4) as usize, 0);
+
+        if self.options.grid_lines {
+            self.fill_rect(0, 0, px_width, px_height, self.options.palette.grid);
+        }
+        for row in 0..height {
+            for col in 0..width {
+                self.fill_cell(row, col, color((row * width + col) as usize));
+            }
+        }
+    }
+
+    fn fill_cell(&mut self, row: u32, col: u32, color: Color) {
+        let pitch = self.options.pitch();
+        let offset = self.options.grid_lines as u32;
+        let size = self.options.cell_size;
+        self.fill_rect(
+            col * pitch + offset,
+            row * pitch + offset,
+            size,
+            size,
+            color,
+        );
+    }
+
+    fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Color) {
+        for py in y..y + height {
+            let start = ((py * self.width + x) * 4) as usize;
+            let end = start + (width * 4) as usize;
+            for pixel in self.pixels[start..end].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+/// Linear interpolation from `a` to `b`.
+fn blend(a: Color, b: Color, t: f64) -> Color {
+    let mut color = a;
+    for (c, (&a, &b)) in color.iter_mut().zip(a.iter().zip(b.iter())) {
+        *c = (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    }
+    color
+}
+
+/// Compares against a file in `tests/golden`, or overwrites it if the
+/// `UPDATE_GOLDEN` environment variable is set.
+#[cfg(test)]
+fn assert_golden(name: &str, bytes: &[u8]) {
+    let path: std::path::PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+        .iter()
+        .collect();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, bytes).unwrap();
+    }
+    let golden = std::fs::read(&path).unwrap();
+    assert!(golden == bytes, "{} differs", path.display());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Pixels as text: `#` alive, `.` dead, `+` grid lines
+    fn ascii(framebuffer: &Framebuffer) -> String {
+        let palette = framebuffer.options().palette;
+        let mut s = String::new();
+        for y in 0..framebuffer.height() {
+            for x in 0..framebuffer.width() {
+                s.push(match framebuffer.pixel(x, y) {
+                    c if c == palette.alive => '#',
+                    c if c == palette.dead => '.',
+                    c if c == palette.grid => '+',
+                    c => panic!("unexpected color {c:?}"),
+                });
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    fn glider() -> Grid {
+        crate::parse_plaintext("!\n.O.\n..O\nOOO\n").unwrap()
+    }
+
+    #[test]
+    fn test_golden_grid_lines() {
+        let framebuffer = Framebuffer::of_grid(
+            &glider(),
+            RenderOptions {
+                cell_size: 2,
+                ..Default::default()
+            },
+        );
+        assert_eq!((10, 10), (framebuffer.width(), framebuffer.height()));
+        let expected = "\
+++++++++++
++..+##+..+
++..+##+..+
+++++++++++
++..+..+##+
++..+..+##+
+++++++++++
++##+##+##+
++##+##+##+
+++++++++++
+";
+        assert_eq!(expected, ascii(&framebuffer));
+    }
+
+    #[test]
+    fn test_golden_no_grid_lines() {
+        let framebuffer = Framebuffer::of_grid(
+            &glider(),
+            RenderOptions {
+                cell_size: 1,
+                grid_lines: false,
+                ..Default::default()
+            },
+        );
+        assert_eq!(".#.\n..#\n###\n", ascii(&framebuffer));
+    }
+
+    #[test]
+    fn test_draw_delta_matches_full() {
+        let mut universe = Universe::new(6, 6);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        let mut delta = Framebuffer::new(3, true);
+        delta.draw(&universe);
+
+        for _ in 0..4 {
+            universe.tick_delta();
+            delta.draw_delta(&universe);
+            let mut full = Framebuffer::new(3, true);
+            full.draw(&universe);
+            assert_eq!(ascii(&full), ascii(&delta));
+        }
+    }
+
+    #[test]
+    fn test_color_modes() {
+        let mut universe = Universe::new(3, 3);
+        universe.set_cells(&[(1, 0), (1, 1), (1, 2)]);
+        universe.set_track_activity(true);
+        universe.tick_delta();
+
+        let palette = Palette::default();
+        let mut framebuffer = Framebuffer::new(1, false);
+        framebuffer.set_color_mode(ColorMode::Age);
+        framebuffer.draw_delta(&universe);
+        // all cells are alive on a 3x3 torus after one tick, only the
+        // middle row survived
+        assert_eq!(palette.newborn, framebuffer.pixel(0, 0));
+        assert_ne!(palette.newborn, framebuffer.pixel(1, 1));
+
+        framebuffer.set_color_mode(ColorMode::Heat);
+        universe.tick_delta();
+        framebuffer.draw_delta(&universe);
+        assert_eq!(palette.heat, framebuffer.pixel(0, 0));
+
+        // falls back to plain colors without tracking
+        universe.set_track_activity(false);
+        framebuffer.draw(&universe);
+        assert_eq!(palette.dead, framebuffer.pixel(0, 0));
+    }
+
+    #[test]
+    fn test_overlay_colors() {
+        let mut universe = Universe::new(3, 1);
+        universe.set_cells(&[(0, 0)]);
+        universe.set_overlay_enabled(true);
+        universe.toggle_cell(0, 0);
+        universe.toggle_boundary(0, 1);
+        universe.toggle_mark(0, 2);
+
+        let palette = Palette::default();
+        let mut framebuffer = Framebuffer::new(1, false);
+        framebuffer.draw(&universe);
+        assert_eq!(palette.history, framebuffer.pixel(0, 0));
+        assert_eq!(palette.boundary, framebuffer.pixel(1, 0));
+        assert_eq!(
+            blend(palette.dead, palette.mark, 0.3),
+            framebuffer.pixel(2, 0)
+        );
+    }
+
+    #[test]
+    fn test_set_colors() {
+        let mut framebuffer = Framebuffer::new(1, false);
+        framebuffer.set_colors(0xFF0000FF, 0x00FF00FF, 0x0000FFFF);
+        framebuffer.draw(&Universe::of_cells(2, 1, vec![Cell::Alive, Cell::Dead]));
+        assert_eq!([0xFF, 0, 0, 0xFF], framebuffer.pixel(0, 0));
+        assert_eq!([0, 0xFF, 0, 0xFF], framebuffer.pixel(1, 0));
+    }
+}
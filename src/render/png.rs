@@ -0,0 +1,188 @@
+//! PNG encoding of rendered cells
+//!
+//! Images are 8-bit RGBA, with every row filtered against the one above so
+//! the repeated rows of each cell compress to almost nothing.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+use super::{Framebuffer, RenderOptions};
+use crate::{Grid, Universe};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+const BIT_DEPTH: u8 = 8;
+const COLOR_TYPE_RGBA: u8 = 6;
+const FILTER_UP: u8 = 2;
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+/// Encodes RGBA pixels as a PNG image.
+///
+/// # Panics
+///
+/// If `pixels` does not hold `width * height` pixels, or either is 0.
+pub fn encode_png(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    assert!(width > 0 && height > 0, "PNG images can't be empty");
+    let stride = width as usize * 4;
+    assert_eq!(stride * height as usize, pixels.len());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // deflate compression, adaptive filtering, no interlacing
+    header.extend_from_slice(&[BIT_DEPTH, COLOR_TYPE_RGBA, 0, 0, 0]);
+
+    let mut filtered = Vec::with_capacity((stride + 1) * height as usize);
+    let mut above: &[u8] = &vec![0; stride];
+    for row in pixels.chunks_exact(stride) {
+        filtered.push(FILTER_UP);
+        filtered.extend(row.iter().zip(above).map(|(&x, &b)| x.wrapping_sub(b)));
+        above = row;
+    }
+
+    let mut out = SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &header);
+    write_chunk(&mut out, b"IDAT", &compress_to_vec_zlib(&filtered, 9));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &b| {
+        CRC_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// PNG methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Framebuffer {
+    /// Encodes the buffer as a PNG image, or returns `None` if nothing has
+    /// been drawn, as PNG images can't be empty.
+    pub fn to_png(&self) -> Option<Vec<u8>> {
+        (self.width() > 0 && self.height() > 0)
+            .then(|| encode_png(self.width(), self.height(), self.as_bytes()))
+    }
+}
+
+impl Grid {
+    /// Renders the grid as a PNG image, or returns `None` if it has no cells.
+    pub fn to_png(&self, options: RenderOptions) -> Option<Vec<u8>> {
+        Framebuffer::of_grid(self, options).to_png()
+    }
+}
+
+impl Universe {
+    /// Renders a region of the universe as a PNG image, clipped to the
+    /// universe.
+    ///
+    /// Returns `None` if no cells are left after clipping. See
+    /// [`Framebuffer::draw_region`].
+    pub fn region_to_png(
+        &self,
+        row: u32,
+        col: u32,
+        width: u32,
+        height: u32,
+        options: RenderOptions,
+    ) -> Option<Vec<u8>> {
+        let mut framebuffer = Framebuffer::with_options(options);
+        framebuffer.draw_region(self, row, col, width, height);
+        framebuffer.to_png()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::assert_golden;
+
+    fn decode(png_bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+        let decoder = png::Decoder::new(png_bytes);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(png::ColorType::Rgba, info.color_type);
+        (info.width, info.height, pixels)
+    }
+
+    fn glider() -> Grid {
+        crate::parse_plaintext("!\n.O.\n..O\nOOO\n").unwrap()
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(0xAE42_6082, crc32(b"IEND"));
+    }
+
+    #[test]
+    fn test_golden_glider() {
+        let options = RenderOptions {
+            cell_size: 4,
+            ..Default::default()
+        };
+        let png_bytes = glider().to_png(options).unwrap();
+        assert_golden("glider.png", &png_bytes);
+
+        let framebuffer = Framebuffer::of_grid(&glider(), options);
+        let (width, height, pixels) = decode(&png_bytes);
+        assert_eq!((16, 16), (width, height));
+        assert_eq!(framebuffer.as_bytes(), pixels);
+    }
+
+    #[test]
+    fn test_golden_region_colors() {
+        let mut universe = Universe::new(8, 8);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        let mut options = RenderOptions {
+            cell_size: 3,
+            grid_lines: false,
+            ..Default::default()
+        };
+        options.palette.alive = [0x20, 0x80, 0x20, 0xFF];
+        options.palette.dead = [0x10, 0x10, 0x10, 0xFF];
+        let png_bytes = universe.region_to_png(1, 1, 3, 10, options).unwrap();
+        assert_golden("glider_region.png", &png_bytes);
+
+        // clipped to 3 by 7 cells
+        let (width, height, pixels) = decode(&png_bytes);
+        assert_eq!((9, 21), (width, height));
+        // cells 1, 1 and 1, 2 of the universe
+        assert_eq!(&options.palette.dead, &pixels[..4]);
+        assert_eq!(&options.palette.alive, &pixels[3 * 4..4 * 4]);
+
+        // clipped to nothing
+        assert_eq!(None, universe.region_to_png(8, 0, 3, 3, options));
+        assert_eq!(None, universe.region_to_png(0, 0, 0, 3, options));
+        assert_eq!(None, Grid::new(0, 0).to_png(options));
+    }
+}
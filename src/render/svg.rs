@@ -0,0 +1,360 @@
+//! SVG rendering of patterns for print and diagrams
+//!
+//! Runs of same colored cells are merged into rectangles, extended down over
+//! following rows with the same run, so a pattern takes a handful of elements
+//! instead of one per cell. Sizes are in user units, one per pixel of the
+//! [`Framebuffer`] rendering with the same [`RenderOptions`].
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use super::{Color, Framebuffer, RenderOptions};
+use crate::{Cell, Grid, Universe};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SvgOptions {
+    /// Cell size, grid lines and colors, as for the other renderers
+    pub render: RenderOptions,
+    /// Label every `n`th row and column with its number, or none if 0
+    pub label_step: u32,
+    /// Cells drawn over with the highlight color, as `(row, col)`
+    pub highlight: HashSet<(u32, u32)>,
+    pub highlight_color: Color,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            render: RenderOptions::default(),
+            label_step: 0,
+            highlight: HashSet::new(),
+            highlight_color: [0xFF, 0xC0, 0x00, 0x80],
+        }
+    }
+}
+
+/// SVG methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Framebuffer {
+    /// Renders the universe's selection, or all of it, as an SVG image with
+    /// this buffer's options, labelling every `label_step`th row and column
+    /// unless 0.
+    #[cfg(feature = "wasm")]
+    pub fn to_svg(&self, universe: &Universe, label_step: u32) -> String {
+        let (row, col, width, height) = match universe.get_selection() {
+            Some(s) => (s.row, s.col, s.width, s.height),
+            None => (0, 0, universe.width(), universe.height()),
+        };
+        let options = SvgOptions {
+            render: *self.options(),
+            label_step,
+            ..Default::default()
+        };
+        universe.region_to_svg(row, col, width, height, &options)
+    }
+}
+
+impl Grid {
+    /// Renders the grid as an SVG image.
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let palette = &options.render.palette;
+        write_svg(
+            self.width as u32,
+            self.height as u32,
+            (0, 0),
+            options,
+            |i| match self.cells[i] {
+                Cell::Alive => Some(palette.alive),
+                Cell::Dead => None,
+            },
+        )
+    }
+}
+
+impl Universe {
+    /// Renders a region of the universe as an SVG image, clipped to the
+    /// universe, colored according to the color mode and overlay.
+    ///
+    /// Labels and highlighted cells use the universe's coordinates.
+    pub fn region_to_svg(
+        &self,
+        row: u32,
+        col: u32,
+        width: u32,
+        height: u32,
+        options: &SvgOptions,
+    ) -> String {
+        let width = width.min(self.width().saturating_sub(col));
+        let height = height.min(self.height().saturating_sub(row));
+        let dead = options.render.palette.dead;
+        write_svg(width, height, (row, col), options, |i| {
+            let (r, c) = (row + i as u32 / width, col + i as u32 % width);
+            let color = options.render.cell_color(self, self.get_index(r, c));
+            (color != dead).then_some(color)
+        })
+    }
+}
+
+/// Cells covered by a rectangle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Rect {
+    col: u32,
+    row: u32,
+    width: u32,
+    height: u32,
+}
+
+/// Writes an SVG of `width` by `height` cells, with `origin` the `(row, col)`
+/// of the top left cell for labels and highlighting, and `color` giving the
+/// colors of cells by index, or `None` for the dead color.
+fn write_svg(
+    width: u32,
+    height: u32,
+    origin: (u32, u32),
+    options: &SvgOptions,
+    color: impl Fn(usize) -> Option<Color>,
+) -> String {
+    let render = &options.render;
+    let palette = &render.palette;
+    let pitch = render.pitch();
+    let lines = render.grid_lines as u32;
+    let (grid_width, grid_height) = (render.pixels_for(width), render.pixels_for(height));
+
+    // room for labels above and left of the cells
+    let font_size = pitch.max(8);
+    let (left, top) = if options.label_step > 0 {
+        let digits = (origin.0 + height).max(1).ilog10() + 1;
+        (font_size * (digits + 1) * 3 / 5, font_size * 3 / 2)
+    } else {
+        (0, 0)
+    };
+    let (total_width, total_height) = (left + grid_width, top + grid_height);
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{total_width}\" height=\"{total_height}\" viewBox=\"0 0 {total_width} {total_height}\">\n"
+    );
+    if (left, top) != (0, 0) {
+        writeln!(out, "<g transform=\"translate({left} {top})\">").unwrap();
+    }
+    writeln!(
+        out,
+        "<rect width=\"{grid_width}\" height=\"{grid_height}\"{}/>",
+        paint("fill", palette.dead)
+    )
+    .unwrap();
+
+    let write_rects = |out: &mut String, color: Color, rects: &[Rect]| {
+        writeln!(out, "<g{}>", paint("fill", color)).unwrap();
+        for rect in rects {
+            writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                rect.col * pitch + lines,
+                rect.row * pitch + lines,
+                rect.width * pitch - lines,
+                rect.height * pitch - lines,
+            )
+            .unwrap();
+        }
+        out.push_str("</g>\n");
+    };
+    for (color, rects) in merge_cells(width, height, color) {
+        write_rects(&mut out, color, &rects);
+    }
+
+    if render.grid_lines {
+        let mut path = String::new();
+        for col in 0..=width {
+            write!(path, "M{}.5 0V{grid_height}", col * pitch).unwrap();
+        }
+        for row in 0..=height {
+            write!(path, "M0 {}.5H{grid_width}", row * pitch).unwrap();
+        }
+        writeln!(out, "<path d=\"{path}\"{}/>", paint("stroke", palette.grid)).unwrap();
+    }
+
+    let highlighted = merge_cells(width, height, |i| {
+        let cell = (origin.0 + i as u32 / width, origin.1 + i as u32 % width);
+        options
+            .highlight
+            .contains(&cell)
+            .then_some(options.highlight_color)
+    });
+    for (color, rects) in highlighted {
+        write_rects(&mut out, color, &rects);
+    }
+
+    if options.label_step > 0 {
+        let step = options.label_step as usize;
+        let half = pitch / 2 + lines;
+        writeln!(
+            out,
+            "<g font-family=\"monospace\" font-size=\"{font_size}\"{}>",
+            paint("fill", palette.alive)
+        )
+        .unwrap();
+        for col in (origin.1.next_multiple_of(step as u32)..origin.1 + width).step_by(step) {
+            writeln!(
+                out,
+                "<text x=\"{}\" y=\"-{}\" text-anchor=\"middle\">{col}</text>",
+                (col - origin.1) * pitch + half,
+                font_size / 2,
+            )
+            .unwrap();
+        }
+        for row in (origin.0.next_multiple_of(step as u32)..origin.0 + height).step_by(step) {
+            writeln!(
+                out,
+                "<text x=\"-{}\" y=\"{}\" text-anchor=\"end\" dominant-baseline=\"central\">{row}</text>",
+                font_size / 4,
+                (row - origin.0) * pitch + half,
+            )
+            .unwrap();
+        }
+        out.push_str("</g>\n");
+    }
+
+    if (left, top) != (0, 0) {
+        out.push_str("</g>\n");
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// ` name="#rrggbb"`, with an opacity if the color is translucent
+fn paint(name: &str, [r, g, b, a]: Color) -> String {
+    let mut attributes = format!(" {name}=\"#{r:02x}{g:02x}{b:02x}\"");
+    if a != 0xFF {
+        write!(attributes, " {name}-opacity=\"{:.3}\"", a as f64 / 255.0).unwrap();
+    }
+    attributes
+}
+
+/// Merges cells into rectangles of the same color, skipping cells without
+/// one, grouped by color in order of first appearance.
+fn merge_cells(
+    width: u32,
+    height: u32,
+    color: impl Fn(usize) -> Option<Color>,
+) -> Vec<(Color, Vec<Rect>)> {
+    let mut rects = Vec::new();
+    // rects that continue if the next row has the same run, keyed by the
+    // run's first column, width and color
+    let mut open: HashMap<(u32, u32, Color), Rect> = HashMap::new();
+    for row in 0..height {
+        let mut continued = HashMap::new();
+        let mut col = 0;
+        while col < width {
+            let index = |col: u32| (row * width + col) as usize;
+            let Some(run_color) = color(index(col)) else {
+                col += 1;
+                continue;
+            };
+            let start = col;
+            while col < width && color(index(col)) == Some(run_color) {
+                col += 1;
+            }
+            let key = (start, col - start, run_color);
+            let rect = match open.remove(&key) {
+                Some(rect) => Rect {
+                    height: rect.height + 1,
+                    ..rect
+                },
+                None => Rect {
+                    col: start,
+                    row,
+                    width: col - start,
+                    height: 1,
+                },
+            };
+            continued.insert(key, rect);
+        }
+        rects.extend(open.drain().map(|((_, _, color), rect)| (color, rect)));
+        open = continued;
+    }
+    rects.extend(open.drain().map(|((_, _, color), rect)| (color, rect)));
+    rects.sort_by_key(|(_, rect)| (rect.row, rect.col));
+
+    let mut groups: Vec<(Color, Vec<Rect>)> = Vec::new();
+    for (color, rect) in rects {
+        match groups.iter_mut().find(|(c, _)| *c == color) {
+            Some((_, group)) => group.push(rect),
+            None => groups.push((color, vec![rect])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::assert_golden;
+
+    #[test]
+    fn test_merge_cells() {
+        // a 2 by 2 block, a separate cell and a 3 cell run below the block
+        let grid = crate::parse_plaintext("!\nOO.O\nOO..\nOOO.\n").unwrap();
+        let alive = [0, 0, 0, 0xFF];
+        let groups = merge_cells(4, 3, |i| (grid.cells[i] == Cell::Alive).then_some(alive));
+        let rect = |col, row, width, height| Rect {
+            col,
+            row,
+            width,
+            height,
+        };
+        assert_eq!(
+            vec![(
+                alive,
+                vec![rect(0, 0, 2, 2), rect(3, 0, 1, 1), rect(0, 2, 3, 1)]
+            )],
+            groups
+        );
+    }
+
+    #[test]
+    fn test_golden_glider() {
+        let grid = crate::parse_plaintext("!\n.O.\n..O\nOOO\n").unwrap();
+        let options = SvgOptions {
+            render: RenderOptions {
+                cell_size: 10,
+                ..Default::default()
+            },
+            label_step: 1,
+            highlight: HashSet::from([(0, 1), (1, 1)]),
+            ..Default::default()
+        };
+        let svg = grid.to_svg(&options);
+        assert_golden("glider.svg", svg.as_bytes());
+        // the background, one per run and the merged highlight
+        assert_eq!(5, svg.matches("<rect").count());
+        assert!(svg.contains("fill=\"#ffc000\" fill-opacity=\"0.502\""));
+    }
+
+    #[test]
+    fn test_region_labels() {
+        let mut universe = Universe::new(20, 20);
+        universe.set_cells(&[(10, 10), (10, 11)]);
+        let options = SvgOptions {
+            render: RenderOptions {
+                cell_size: 4,
+                grid_lines: false,
+                ..Default::default()
+            },
+            label_step: 5,
+            ..Default::default()
+        };
+        // clipped to 12 by 11 cells
+        let svg = universe.region_to_svg(9, 8, 15, 11, &options);
+        assert!(svg.contains("<rect width=\"48\" height=\"44\" fill=\"#ffffff\"/>"));
+        assert!(svg.contains("<rect x=\"8\" y=\"4\" width=\"8\" height=\"4\"/>"));
+        let parts: Vec<&str> = svg.split("</text>").collect();
+        let labels: Vec<&str> = parts[..parts.len() - 1]
+            .iter()
+            .map(|s| s.rsplit_once('>').unwrap().1)
+            .collect();
+        assert_eq!(vec!["10", "15", "10", "15"], labels);
+    }
+}
@@ -0,0 +1,242 @@
+//! Rendering of universes to text for terminals
+//!
+//! Besides one glyph per cell, cells can be packed two to a half-block
+//! character or eight to a braille pattern, so large universes fit in a
+//! terminal. Colors from a [`Palette`] are written as 24-bit ANSI escape codes.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use std::fmt::Write;
+
+use super::{Color, ColorMode, Palette, RenderOptions};
+use crate::{Cell, Universe};
+
+/// Characters used to draw cells.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TextStyle {
+    /// One `◼` or `◻` per cell
+    #[default]
+    Squares,
+    /// A column of two cells per `▀`, `▄` or `█`
+    HalfBlock,
+    /// Two columns of four cells per braille pattern
+    Braille,
+}
+
+impl TextStyle {
+    /// Columns and rows of cells drawn by each character.
+    pub fn cells_per_char(self) -> (u32, u32) {
+        match self {
+            TextStyle::Squares => (1, 1),
+            TextStyle::HalfBlock => (1, 2),
+            TextStyle::Braille => (2, 4),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct TextOptions {
+    pub style: TextStyle,
+    /// Color cells with ANSI escape codes
+    pub ansi_color: bool,
+    pub palette: Palette,
+    pub color_mode: ColorMode,
+}
+
+/// Braille dot of each cell in a character, indexed by `[row][col]`
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+const BRAILLE_BLANK: u32 = 0x2800;
+
+/// Text rendering methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Returns the universe as text in the given style, without colors.
+    pub fn render_styled(&self, style: TextStyle) -> String {
+        self.render_text(&TextOptions {
+            style,
+            ..Default::default()
+        })
+    }
+}
+
+impl Universe {
+    /// Returns the universe as text, one line per row of characters.
+    pub fn render_text(&self, options: &TextOptions) -> String {
+        self.render_text_region(0, 0, self.width, self.height, options)
+    }
+
+    /// Returns the cells in a rectangle as text, clipped to the universe.
+    ///
+    /// Characters at the bottom and right edges may be partly outside the
+    /// rectangle; those cells are drawn as dead, without a color.
+    pub fn render_text_region(
+        &self,
+        row: u32,
+        col: u32,
+        width: u32,
+        height: u32,
+        options: &TextOptions,
+    ) -> String {
+        let bottom = row.saturating_add(height).min(self.height);
+        let right = col.saturating_add(width).min(self.width);
+        let colors = RenderOptions {
+            palette: options.palette,
+            color_mode: options.color_mode,
+            ..Default::default()
+        };
+        let cell = |r: u32, c: u32| {
+            (r < bottom && c < right).then(|| {
+                let idx = self.get_index(r, c);
+                (self.cells[idx], colors.cell_color(self, idx))
+            })
+        };
+        let alive = |r, c| matches!(cell(r, c), Some((Cell::Alive, _)));
+
+        let (char_cols, char_rows) = options.style.cells_per_char();
+        let mut out = String::new();
+        for r in (row..bottom).step_by(char_rows as usize) {
+            let mut ansi = Ansi::default();
+            for c in (col..right).step_by(char_cols as usize) {
+                let ch = match options.style {
+                    TextStyle::Squares => {
+                        let (state, color) = cell(r, c).unwrap();
+                        if options.ansi_color {
+                            ansi.set(&mut out, Some(color), None);
+                        }
+                        match state {
+                            Cell::Alive => '◼',
+                            Cell::Dead => '◻',
+                        }
+                    }
+                    TextStyle::HalfBlock if options.ansi_color => {
+                        let top = cell(r, c).map(|(_, color)| color);
+                        let bottom = cell(r + 1, c).map(|(_, color)| color);
+                        ansi.set(&mut out, top, bottom);
+                        '▀'
+                    }
+                    TextStyle::HalfBlock => match (alive(r, c), alive(r + 1, c)) {
+                        (false, false) => ' ',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (true, true) => '█',
+                    },
+                    TextStyle::Braille => {
+                        let mut dots = 0;
+                        let mut fg = None;
+                        for (dr, row_dots) in BRAILLE_DOTS.iter().enumerate() {
+                            for (dc, dot) in row_dots.iter().enumerate() {
+                                if let Some((Cell::Alive, color)) =
+                                    cell(r + dr as u32, c + dc as u32)
+                                {
+                                    dots |= dot;
+                                    fg.get_or_insert(color);
+                                }
+                            }
+                        }
+                        if options.ansi_color {
+                            ansi.set(&mut out, fg.or(ansi.fg), Some(options.palette.dead));
+                        }
+                        char::from_u32(BRAILLE_BLANK + dots).unwrap()
+                    }
+                };
+                out.push(ch);
+            }
+            if options.ansi_color {
+                out.push_str(Ansi::RESET);
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Colors set by the escape codes written so far on a line.
+#[derive(Default)]
+struct Ansi {
+    fg: Option<Color>,
+    bg: Option<Color>,
+}
+
+impl Ansi {
+    const RESET: &'static str = "\x1b[0m";
+
+    /// Writes escape codes for colors that changed, `None` being the
+    /// terminal's default.
+    fn set(&mut self, out: &mut String, fg: Option<Color>, bg: Option<Color>) {
+        if fg != self.fg {
+            match fg {
+                Some([r, g, b, _]) => write!(out, "\x1b[38;2;{r};{g};{b}m").unwrap(),
+                None => out.push_str("\x1b[39m"),
+            }
+            self.fg = fg;
+        }
+        if bg != self.bg {
+            match bg {
+                Some([r, g, b, _]) => write!(out, "\x1b[48;2;{r};{g};{b}m").unwrap(),
+                None => out.push_str("\x1b[49m"),
+            }
+            self.bg = bg;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn glider() -> Universe {
+        let mut universe = Universe::new(5, 5);
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        universe
+    }
+
+    #[test]
+    fn test_squares() {
+        let text = glider().render_styled(TextStyle::Squares);
+        assert_eq!("◻◼◻◻◻\n◻◻◼◻◻\n◼◼◼◻◻\n◻◻◻◻◻\n◻◻◻◻◻\n", text);
+    }
+
+    #[test]
+    fn test_half_block() {
+        let text = glider().render_styled(TextStyle::HalfBlock);
+        assert_eq!(" ▀▄  \n▀▀▀  \n     \n", text);
+    }
+
+    #[test]
+    fn test_braille() {
+        let text = glider().render_styled(TextStyle::Braille);
+        assert_eq!("⠬⠆⠀\n⠀⠀⠀\n", text);
+    }
+
+    #[test]
+    fn test_region_clips() {
+        let universe = glider();
+        let options = TextOptions {
+            style: TextStyle::HalfBlock,
+            ..Default::default()
+        };
+        assert_eq!("▀ \n", universe.render_text_region(1, 2, 2, 1, &options));
+        assert_eq!("", universe.render_text_region(5, 0, 3, 3, &options));
+    }
+
+    #[test]
+    fn test_ansi_color() {
+        let mut universe = Universe::new(2, 2);
+        universe.set_cells(&[(0, 0)]);
+        let options = TextOptions {
+            style: TextStyle::HalfBlock,
+            ansi_color: true,
+            ..Default::default()
+        };
+        let black = "\x1b[38;2;0;0;0m";
+        let white_fg = "\x1b[38;2;255;255;255m";
+        let white_bg = "\x1b[48;2;255;255;255m";
+        assert_eq!(
+            format!("{black}{white_bg}▀{white_fg}▀\x1b[0m\n"),
+            universe.render_text(&options)
+        );
+    }
+}
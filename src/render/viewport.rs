@@ -0,0 +1,424 @@
+//! Zoomable, pannable view onto a universe
+//!
+//! A [`Viewport`] maps a fixed-size screen onto universe cells independently
+//! of the universe's dimensions. Zoom levels are powers of two: at zoom `z >= 0`
+//! each cell is `2^z` pixels wide, and at `z < 0` each pixel covers a block of
+//! `2^-z` by `2^-z` cells, shaded by how many of them are alive.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use super::{blend, Color, Framebuffer};
+use crate::{Cell, Universe};
+
+pub const MIN_ZOOM: i32 = -6;
+pub const MAX_ZOOM: i32 = 6;
+
+/// Smallest cell size in pixels that grid lines are drawn at.
+const GRID_MIN_CELL_SIZE: i64 = 4;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    width: u32,
+    height: u32,
+    /// Cell coordinates at the centre of the screen
+    center_row: f64,
+    center_col: f64,
+    zoom: i32,
+}
+
+/// public methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Viewport {
+    /// Creates a viewport of `width` by `height` pixels showing the cell at
+    /// 0, 0 in the centre at 1 pixel per cell.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(width: u32, height: u32) -> Viewport {
+        Viewport {
+            width,
+            height,
+            center_row: 0.0,
+            center_col: 0.0,
+            zoom: 0,
+        }
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Changes the screen size, keeping the same centre.
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Log2 of the number of pixels per cell.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn zoom(&self) -> i32 {
+        self.zoom
+    }
+
+    /// Sets the zoom level, keeping the same centre.
+    ///
+    /// Clamped between [`MIN_ZOOM`] and [`MAX_ZOOM`].
+    #[cfg_attr(feature = "wasm", wasm_bindgen(setter))]
+    pub fn set_zoom(&mut self, zoom: i32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn center_row(&self) -> f64 {
+        self.center_row
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn center_col(&self) -> f64 {
+        self.center_col
+    }
+
+    /// Centres the view on a (possibly fractional) cell position.
+    pub fn set_center(&mut self, row: f64, col: f64) {
+        self.center_row = row;
+        self.center_col = col;
+    }
+
+    /// Changes the zoom level by `delta`, keeping the cell under the screen
+    /// position `x`, `y` in place.
+    pub fn zoom_at(&mut self, delta: i32, x: f64, y: f64) {
+        let (row, col) = self.screen_to_cell_f64(x, y);
+        self.set_zoom(self.zoom + delta);
+        let scale = self.scale();
+        self.center_col = col - (x - self.width as f64 / 2.0) / scale;
+        self.center_row = row - (y - self.height as f64 / 2.0) / scale;
+    }
+
+    /// Moves the view so the contents shift by `dx`, `dy` pixels.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        let scale = self.scale();
+        self.center_col -= dx / scale;
+        self.center_row -= dy / scale;
+    }
+
+    /// Centres a universe of `width` by `height` cells, zooming to the
+    /// largest level at which it fits on screen.
+    pub fn fit(&mut self, width: u32, height: u32) {
+        self.center_row = height as f64 / 2.0;
+        self.center_col = width as f64 / 2.0;
+        let fits = |zoom: i32| {
+            let scale = 2f64.powi(zoom);
+            width as f64 * scale <= self.width as f64 && height as f64 * scale <= self.height as f64
+        };
+        self.zoom = (MIN_ZOOM..=MAX_ZOOM)
+            .rev()
+            .find(|&z| fits(z))
+            .unwrap_or(MIN_ZOOM);
+    }
+
+    /// Row of the cell under screen position `y`.
+    ///
+    /// When zoomed out, the top row of the block of cells under the pixel.
+    pub fn row_at(&self, y: f64) -> i32 {
+        self.screen_to_cell(0.0, y).0
+    }
+
+    /// Column of the cell under screen position `x`.
+    ///
+    /// When zoomed out, the leftmost column of the block of cells under the
+    /// pixel.
+    pub fn col_at(&self, x: f64) -> i32 {
+        self.screen_to_cell(x, 0.0).1
+    }
+
+    /// Screen position of the top edge of `row`.
+    pub fn y_of(&self, row: i32) -> f64 {
+        self.cell_to_screen(row, 0).1
+    }
+
+    /// Screen position of the left edge of `col`.
+    pub fn x_of(&self, col: i32) -> f64 {
+        self.cell_to_screen(0, col).0
+    }
+}
+
+impl Viewport {
+    /// Cell under a screen position, as `(row, col)`.
+    ///
+    /// Coordinates may be outside of the universe.
+    pub fn screen_to_cell(&self, x: f64, y: f64) -> (i32, i32) {
+        let (ox, oy) = self.origin();
+        let to_cell = |p: f64, o: i64| match self.zoom {
+            z if z >= 0 => (o + p.floor() as i64).div_euclid(1 << z),
+            z => (o + p.floor() as i64) << -z,
+        } as i32;
+        (to_cell(y, oy), to_cell(x, ox))
+    }
+
+    /// Screen position of the top left corner of a cell, as `(x, y)`.
+    pub fn cell_to_screen(&self, row: i32, col: i32) -> (f64, f64) {
+        let (ox, oy) = self.origin();
+        let scale = self.scale();
+        (
+            (col as f64 * scale).floor() - ox as f64,
+            (row as f64 * scale).floor() - oy as f64,
+        )
+    }
+
+    fn scale(&self) -> f64 {
+        2f64.powi(self.zoom)
+    }
+
+    fn screen_to_cell_f64(&self, x: f64, y: f64) -> (f64, f64) {
+        let scale = self.scale();
+        (
+            self.center_row + (y - self.height as f64 / 2.0) / scale,
+            self.center_col + (x - self.width as f64 / 2.0) / scale,
+        )
+    }
+
+    /// Position of the top left pixel in zoomed pixel space, where cell
+    /// boundaries fall on whole pixels.
+    fn origin(&self) -> (i64, i64) {
+        let scale = self.scale();
+        (
+            (self.center_col * scale - self.width as f64 / 2.0).round() as i64,
+            (self.center_row * scale - self.height as f64 / 2.0).round() as i64,
+        )
+    }
+}
+
+/// Viewport drawing methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Framebuffer {
+    /// Redraws the part of the universe visible in a viewport.
+    ///
+    /// The buffer is resized to the viewport, and the cell size option is
+    /// ignored in favor of the viewport's zoom level. Areas outside of the
+    /// universe use the grid color.
+    pub fn draw_viewport(&mut self, universe: &Universe, viewport: &Viewport) {
+        self.width = viewport.width;
+        self.height = viewport.height;
+        self.pixels.clear();
+        self.pixels
+            .resize((viewport.width * viewport.height * 4) as usize, 0);
+        self.redraw_pixels(universe, viewport, 0, 0, viewport.width, viewport.height);
+    }
+
+    /// Redraws only the pixels showing cells in the universe's delta buffers.
+    ///
+    /// Falls back to [`draw_viewport`](Self::draw_viewport) if the buffer does
+    /// not match the viewport's size, or if the color mode changes colors of
+    /// cells that did not change state.
+    pub fn draw_viewport_delta(&mut self, universe: &Universe, viewport: &Viewport) {
+        if (self.width, self.height) != (viewport.width, viewport.height)
+            || self.options.is_animated()
+        {
+            return self.draw_viewport(universe, viewport);
+        }
+
+        let (ox, oy) = viewport.origin();
+        for delta in [&universe.delta_alive, &universe.delta_dead] {
+            for pair in delta.chunks_exact(2) {
+                let (row, col) = (pair[0] as i64, pair[1] as i64);
+                let (x, y, size) = match viewport.zoom {
+                    z if z >= 0 => (col << z, row << z, 1 << z),
+                    z => (col >> -z, row >> -z, 1),
+                };
+                let (x, y) = (x - ox, y - oy);
+                // clip to the screen
+                let (x0, y0) = (x.max(0), y.max(0));
+                let x1 = (x + size).min(self.width as i64);
+                let y1 = (y + size).min(self.height as i64);
+                if x0 >= x1 || y0 >= y1 {
+                    continue;
+                }
+                let (x0, y0) = (x0 as u32, y0 as u32);
+                let (w, h) = (x1 as u32 - x0, y1 as u32 - y0);
+                self.redraw_pixels(universe, viewport, x0, y0, w, h);
+            }
+        }
+    }
+}
+
+impl Framebuffer {
+    fn redraw_pixels(
+        &mut self,
+        universe: &Universe,
+        viewport: &Viewport,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) {
+        let (ox, oy) = viewport.origin();
+        for py in y..y + height {
+            for px in x..x + width {
+                let color = self.viewport_pixel(universe, viewport, ox + px as i64, oy + py as i64);
+                let i = ((py * self.width + px) * 4) as usize;
+                self.pixels[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Color of a pixel in zoomed pixel space.
+    fn viewport_pixel(&self, universe: &Universe, viewport: &Viewport, x: i64, y: i64) -> Color {
+        let palette = &self.options.palette;
+        let (width, height) = (universe.width() as i64, universe.height() as i64);
+
+        if viewport.zoom >= 0 {
+            let size = 1 << viewport.zoom;
+            if self.options.grid_lines
+                && size >= GRID_MIN_CELL_SIZE
+                && (x.rem_euclid(size) == 0 || y.rem_euclid(size) == 0)
+            {
+                return palette.grid;
+            }
+            let (row, col) = (y.div_euclid(size), x.div_euclid(size));
+            if !(0..height).contains(&row) || !(0..width).contains(&col) {
+                return palette.grid;
+            }
+            return self
+                .options
+                .cell_color(universe, (row * width + col) as usize);
+        }
+
+        // count alive cells in the block under the pixel
+        let size = 1 << -viewport.zoom;
+        let rows = (y * size).max(0)..(y * size + size).min(height);
+        let cols = (x * size).max(0)..(x * size + size).min(width);
+        if rows.is_empty() || cols.is_empty() {
+            return palette.grid;
+        }
+        let total = (rows.end - rows.start) * (cols.end - cols.start);
+        let alive: i64 = rows
+            .map(|row| {
+                let start = (row * width) as usize;
+                universe.cells[start + cols.start as usize..start + cols.end as usize]
+                    .iter()
+                    .filter(|&&c| c == Cell::Alive)
+                    .count() as i64
+            })
+            .sum();
+        blend(palette.dead, palette.alive, alive as f64 / total as f64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_screen_to_cell() {
+        let mut viewport = Viewport::new(100, 80);
+        viewport.set_center(10.0, 20.0);
+        viewport.set_zoom(2);
+        assert_eq!((10, 20), viewport.screen_to_cell(50.0, 40.0));
+        assert_eq!((10, 19), viewport.screen_to_cell(49.0, 40.0));
+        assert_eq!((0, 7), viewport.screen_to_cell(0.0, 0.0));
+        assert_eq!((50.0, 40.0), viewport.cell_to_screen(10, 20));
+
+        viewport.set_zoom(-2);
+        assert_eq!((8, 20), viewport.screen_to_cell(50.0, 40.0));
+    }
+
+    #[test]
+    fn test_zoom_at_keeps_cell_under_cursor() {
+        let mut viewport = Viewport::new(200, 200);
+        viewport.set_center(50.0, 50.0);
+        let before = viewport.screen_to_cell(30.0, 170.0);
+        for delta in [1, 2, -1, -3, 2] {
+            viewport.zoom_at(delta, 30.0, 170.0);
+            let after = viewport.screen_to_cell(30.0, 170.0);
+            assert!((before.0 - after.0).abs() <= 1, "{before:?} {after:?}");
+            assert!((before.1 - after.1).abs() <= 1, "{before:?} {after:?}");
+        }
+        viewport.set_zoom(100);
+        assert_eq!(MAX_ZOOM, viewport.zoom());
+    }
+
+    #[test]
+    fn test_pan() {
+        let mut viewport = Viewport::new(100, 100);
+        viewport.set_zoom(3);
+        viewport.pan(16.0, -8.0);
+        assert_eq!((1.0, -2.0), (viewport.center_row(), viewport.center_col()));
+    }
+
+    #[test]
+    fn test_fit() {
+        let mut viewport = Viewport::new(800, 600);
+        viewport.fit(64, 64);
+        assert_eq!(3, viewport.zoom());
+        viewport.fit(4096, 4096);
+        assert_eq!(-3, viewport.zoom());
+        assert_eq!(
+            (0, 0),
+            viewport.screen_to_cell(400.0 - 256.0, 300.0 - 256.0)
+        );
+    }
+
+    #[test]
+    fn test_draw_zoomed_in() {
+        let universe = Universe::of_cells(2, 1, vec![Cell::Alive, Cell::Dead]);
+        let mut viewport = Viewport::new(6, 2);
+        viewport.set_center(0.5, 1.0);
+        viewport.set_zoom(1);
+        let mut framebuffer = Framebuffer::new(1, false);
+        framebuffer.draw_viewport(&universe, &viewport);
+
+        let palette = framebuffer.options().palette;
+        let row: Vec<_> = (0..6).map(|x| framebuffer.pixel(x, 0)).collect();
+        let (a, d, g) = (palette.alive, palette.dead, palette.grid);
+        assert_eq!(vec![g, a, a, d, d, g], row);
+    }
+
+    #[test]
+    fn test_draw_zoomed_out_shading() {
+        // 2x2 blocks with 0, 2 and 4 alive cells
+        let o = Cell::Alive;
+        let x = Cell::Dead;
+        #[rustfmt::skip]
+        let cells = vec![
+            x, x, o, x, o, o,
+            x, x, o, x, o, o,
+        ];
+        let universe = Universe::of_cells(6, 2, cells);
+        let mut viewport = Viewport::new(3, 1);
+        viewport.set_center(1.0, 3.0);
+        viewport.set_zoom(-1);
+        let mut framebuffer = Framebuffer::new(1, false);
+        framebuffer.draw_viewport(&universe, &viewport);
+
+        let palette = framebuffer.options().palette;
+        assert_eq!(palette.dead, framebuffer.pixel(0, 0));
+        assert_eq!([0x80, 0x80, 0x80, 0xFF], framebuffer.pixel(1, 0));
+        assert_eq!(palette.alive, framebuffer.pixel(2, 0));
+    }
+
+    #[test]
+    fn test_draw_viewport_delta_matches_full() {
+        let mut universe = Universe::new(16, 16);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        for zoom in [-2, 0, 3] {
+            let mut viewport = Viewport::new(40, 30);
+            viewport.set_center(4.0, 5.0);
+            viewport.set_zoom(zoom);
+            let mut delta = Framebuffer::new(1, true);
+            delta.draw_viewport(&universe, &viewport);
+            for _ in 0..6 {
+                universe.tick_delta();
+                delta.draw_viewport_delta(&universe, &viewport);
+                let mut full = Framebuffer::new(1, true);
+                full.draw_viewport(&universe, &viewport);
+                assert_eq!(full.as_bytes(), delta.as_bytes(), "zoom {zoom}");
+            }
+        }
+    }
+}
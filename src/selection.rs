@@ -0,0 +1,309 @@
+//! Rectangular selection and clipboard operations
+//!
+//! The selection is a rectangle of cells inside the universe that region
+//! operations apply to. The clipboard holds a copied [`Grid`] which can be
+//! pasted back anywhere, or exchanged with other programs as RLE.
+
+#[cfg(feature = "wasm")]
+use js_sys::TypeError;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{prelude::*, JsValue};
+
+use crate::{write_rle, Cell, Grid, PasteMode, SoupSymmetry, Symmetry, Universe};
+
+/// A rectangle of cells, always inside the universe it was made for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub row: u32,
+    pub col: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Selection {
+    /// Whether the selection is all of a universe.
+    fn covers(&self, width: u32, height: u32) -> bool {
+        (self.row, self.col, self.width, self.height) == (0, 0, width, height)
+    }
+}
+
+/// Selection and clipboard methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Selects a rectangle of cells, clipped to the universe.
+    ///
+    /// Selecting an empty rectangle clears the selection.
+    pub fn select(&mut self, row: u32, col: u32, width: u32, height: u32) {
+        let row = row.min(self.height);
+        let col = col.min(self.width);
+        let width = width.min(self.width - col);
+        let height = height.min(self.height - row);
+        self.selection = (width > 0 && height > 0).then_some(Selection {
+            row,
+            col,
+            width,
+            height,
+        });
+    }
+
+    /// Selects the whole universe.
+    pub fn select_all(&mut self) {
+        self.select(0, 0, self.width, self.height);
+    }
+
+    /// Shrinks the selection to the bounding box of its alive cells, clearing
+    /// it if there are none.
+    pub fn select_bounding_box(&mut self) {
+        if let Some(Selection {
+            row,
+            col,
+            width,
+            height,
+        }) = self.selection
+        {
+            match self.region(row, col, width, height).bounding_box() {
+                Some((x, y, w, h)) => {
+                    self.select(row + y as u32, col + x as u32, w as u32, h as u32)
+                }
+                None => self.deselect(),
+            }
+        }
+    }
+
+    pub fn deselect(&mut self) {
+        self.selection = None;
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.selection.is_some()
+    }
+
+    /// The selection as `[row, col, width, height]`, or an empty array if
+    /// nothing is selected.
+    pub fn selection(&self) -> Vec<u32> {
+        self.selection
+            .map_or(Vec::new(), |s| vec![s.row, s.col, s.width, s.height])
+    }
+
+    /// Copies the selected cells to the clipboard.
+    ///
+    /// Returns `false` if nothing is selected.
+    pub fn copy(&mut self) -> bool {
+        match self.selected_region() {
+            Some(grid) => {
+                self.clipboard = Some(grid);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copies the selected cells to the clipboard and clears them.
+    ///
+    /// Returns `false` if nothing is selected.
+    pub fn cut(&mut self) -> bool {
+        self.copy() && self.clear_selected()
+    }
+
+    /// Pastes the clipboard with its top left corner at `row`, `col`, and
+    /// selects the pasted area inside the universe, not counting parts that
+    /// wrapped around.
+    ///
+    /// See [`paste`](Self::paste). Returns `false` if the clipboard is empty.
+    pub fn paste_clipboard(&mut self, row: i32, col: i32, mode: PasteMode) -> bool {
+        let Some(grid) = self.clipboard.take() else {
+            return false;
+        };
+        self.paste(&grid, row, col, mode);
+        let (top, left) = (row.max(0) as u32, col.max(0) as u32);
+        let width = (col + grid.width as i32).max(0) as u32 - left;
+        let height = (row + grid.height as i32).max(0) as u32 - top;
+        self.select(top, left, width, height);
+        self.clipboard = Some(grid);
+        true
+    }
+
+    /// Kills the selected cells.
+    ///
+    /// Returns `false` if nothing is selected.
+    pub fn clear_selected(&mut self) -> bool {
+        self.map_selected(|_cell| Cell::Dead)
+    }
+
+    /// Toggles the selected cells.
+    ///
+    /// Returns `false` if nothing is selected.
+    pub fn invert_selected(&mut self) -> bool {
+        self.map_selected(|cell| match cell {
+            Cell::Alive => Cell::Dead,
+            Cell::Dead => Cell::Alive,
+        })
+    }
+
+    /// Overwrites the selected cells with a random soup generated from `seed`.
+    ///
+    /// See [`Grid::random_soup`]. Returns `false` if nothing is selected.
+    pub fn fill_random_selected(
+        &mut self,
+        seed: &str,
+        density: f64,
+        symmetry: SoupSymmetry,
+    ) -> bool {
+        let Some(s) = self.selection else {
+            return false;
+        };
+        self.fill_random(s.row, s.col, s.width, s.height, seed, density, symmetry);
+        true
+    }
+
+    /// Transforms the selected cells in place.
+    ///
+    /// See [`transform_region`](Self::transform_region). Returns `false` if
+    /// nothing is selected.
+    pub fn transform_selected(&mut self, symmetry: Symmetry) -> bool {
+        let Some(s) = self.selection else {
+            return false;
+        };
+        self.transform_region(s.row, s.col, s.width, s.height, symmetry);
+        true
+    }
+
+    /// Shrinks the universe to the selection, which then covers all of it.
+    ///
+    /// Like [`resize`](Self::resize), clears the delta buffers and history.
+    /// Returns `false` if nothing is selected.
+    pub fn crop_to_selection(&mut self) -> bool {
+        let Some(s) = self.selection else {
+            return false;
+        };
+        if !s.covers(self.width, self.height) {
+            self.resize_with_offset(s.width, s.height, (-(s.row as i64), -(s.col as i64)));
+        }
+        self.select_all();
+        true
+    }
+
+    /// Width of the clipboard contents, 0 if empty.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn clipboard_width(&self) -> u32 {
+        self.clipboard.as_ref().map_or(0, |g| g.width as u32)
+    }
+
+    /// Height of the clipboard contents, 0 if empty.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn clipboard_height(&self) -> u32 {
+        self.clipboard.as_ref().map_or(0, |g| g.height as u32)
+    }
+
+    /// The clipboard as RLE, for sharing outside of the app.
+    pub fn clipboard_rle(&self) -> Option<String> {
+        self.clipboard.as_ref().map(write_rle)
+    }
+
+    /// Parses a pattern file into the clipboard.
+    #[cfg(feature = "wasm")]
+    pub fn set_clipboard_file(&mut self, f: &[u8]) -> Result<(), JsValue> {
+        let grid = Self::grid_of_file(f).map_err(|e| TypeError::new(e.to_string().as_ref()))?;
+        self.clipboard = Some(grid);
+        Ok(())
+    }
+}
+
+impl Universe {
+    pub fn get_selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    pub fn clipboard(&self) -> Option<&Grid> {
+        self.clipboard.as_ref()
+    }
+
+    pub fn set_clipboard(&mut self, grid: Option<Grid>) {
+        self.clipboard = grid;
+    }
+
+    /// Copies the selected cells into a grid.
+    pub fn selected_region(&self) -> Option<Grid> {
+        self.selection
+            .map(|s| self.region(s.row, s.col, s.width, s.height))
+    }
+
+    /// Replaces each selected cell, as a single edit.
+    fn map_selected(&mut self, f: impl Fn(Cell) -> Cell) -> bool {
+        let Some(s) = self.selection else {
+            return false;
+        };
+        let changes: Vec<_> = (s.row..s.row + s.height)
+            .flat_map(|row| (s.col..s.col + s.width).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                let idx = self.get_index(row, col);
+                (idx, f(self.cells[idx]))
+            })
+            .collect();
+        self.edit_cells(changes);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_rle;
+
+    fn glider() -> Universe {
+        let mut universe = Universe::new(8, 8);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        universe
+    }
+
+    #[test]
+    fn test_select_clips() {
+        let mut universe = glider();
+        universe.select(6, 5, 10, 10);
+        assert_eq!(vec![6, 5, 3, 2], universe.selection());
+        universe.select(8, 0, 1, 1);
+        assert!(!universe.has_selection());
+    }
+
+    #[test]
+    fn test_copy_paste_rle() {
+        let mut universe = glider();
+        universe.set_history_budget(1 << 20);
+        assert!(!universe.copy());
+        universe.select_all();
+        universe.select_bounding_box();
+        assert_eq!(vec![1, 1, 3, 3], universe.selection());
+        assert!(universe.cut());
+        assert!(universe.get_cells().iter().all(|&c| c == Cell::Dead));
+
+        let rle = universe.clipboard_rle().unwrap();
+        assert_eq!("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n", rle);
+        assert_eq!(universe.clipboard(), Some(&parse_rle(&rle).unwrap()));
+
+        assert!(universe.paste_clipboard(-1, 6, PasteMode::Or));
+        assert_eq!(vec![0, 6, 2, 2], universe.selection());
+        assert_eq!(".O.\n..O\nOOO\n", universe.region(7, 6, 3, 3).to_string());
+
+        // undo the paste, then the cut
+        universe.undo();
+        universe.undo();
+        assert_eq!(".O.\n..O\nOOO\n", universe.region(1, 1, 3, 3).to_string());
+    }
+
+    #[test]
+    fn test_invert_and_crop() {
+        let mut universe = glider();
+        universe.select(1, 1, 3, 2);
+        assert!(universe.invert_selected());
+        assert_eq!(
+            "O.O\nOO.\n",
+            universe.selected_region().unwrap().to_string()
+        );
+
+        universe.select(1, 1, 3, 3);
+        assert!(universe.crop_to_selection());
+        assert_eq!((3, 3), (universe.width(), universe.height()));
+        assert_eq!(vec![0, 0, 3, 3], universe.selection());
+        assert_eq!("O.O\nOO.\nOOO\n", universe.region(0, 0, 3, 3).to_string());
+    }
+}
@@ -0,0 +1,196 @@
+//! Serde support, behind the `serde` feature
+//!
+//! Cells are stored as the body of an RLE file rather than one element per
+//! cell, so universes stay small and readable in formats like JSON:
+//!
+//! ```json
+//! {"width":8,"height":8,"cells":"bo$2bo$3o!\n"}
+//! ```
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    parse_rle, parse_rle_with_overlay, write_rle_body, write_rle_body_with_overlay, EdgeBehavior,
+    Grid, Universe, LIFE_HISTORY_RULE, RULE,
+};
+
+/// Most cells deserialized, so hostile input cannot exhaust memory
+const MAX_CELLS: usize = 1 << 28;
+
+#[derive(Serialize, Deserialize)]
+struct GridRepr {
+    width: usize,
+    height: usize,
+    /// RLE without the header line
+    cells: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct UniverseRepr {
+    width: u32,
+    height: u32,
+    rule: String,
+    edge_behavior: EdgeBehavior,
+    generation: u32,
+    /// Whether `cells` holds LifeHistory states with the overlay
+    #[serde(default)]
+    overlay: bool,
+    /// RLE without the header line
+    cells: String,
+}
+
+impl Serialize for Grid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GridRepr {
+            width: self.width,
+            height: self.height,
+            cells: write_rle_body(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Grid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = GridRepr::deserialize(deserializer)?;
+        check_size(repr.width, repr.height)?;
+        let rle = format!("x = {}, y = {}\n{}", repr.width, repr.height, repr.cells);
+        parse_rle(&rle).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Universe {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let grid = self.region(0, 0, self.width, self.height);
+        let overlay = self.get_overlay();
+        UniverseRepr {
+            width: self.width,
+            height: self.height,
+            rule: RULE.to_owned(),
+            edge_behavior: self.edge_behavior,
+            generation: self.generation,
+            overlay: overlay.is_some(),
+            cells: match overlay {
+                Some(overlay) => write_rle_body_with_overlay(&grid, overlay),
+                None => write_rle_body(&grid),
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Universe {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = UniverseRepr::deserialize(deserializer)?;
+        if repr.rule != RULE {
+            return Err(D::Error::custom(format_args!(
+                "unsupported rule {:?}",
+                repr.rule
+            )));
+        }
+        check_size(repr.width as usize, repr.height as usize)?;
+
+        let rule = if repr.overlay {
+            LIFE_HISTORY_RULE
+        } else {
+            RULE
+        };
+        let rle = format!(
+            "x = {}, y = {}, rule = {}\n{}",
+            repr.width, repr.height, rule, repr.cells
+        );
+        let (grid, overlay) = parse_rle_with_overlay(&rle).map_err(D::Error::custom)?;
+
+        let mut universe = Universe::of_cells(repr.width, repr.height, grid.cells);
+        universe.edge_behavior = repr.edge_behavior;
+        universe.generation = repr.generation;
+        if repr.overlay {
+            universe.set_overlay(overlay.unwrap_or_else(|| vec![0; universe.cells.len()]));
+        }
+        Ok(universe)
+    }
+}
+
+/// Fails for dimensions with more than [`MAX_CELLS`] cells.
+fn check_size<E: Error>(width: usize, height: usize) -> Result<(), E> {
+    match width.checked_mul(height) {
+        Some(cells) if cells <= MAX_CELLS => Ok(()),
+        _ => Err(E::custom(format_args!(
+            "{width}x{height} pattern is too large"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Cell;
+
+    #[test]
+    fn test_grid_json() {
+        let grid = parse_rle("x = 4, y = 3\nbo$2bo$3o!").unwrap();
+        let json = serde_json::to_string(&grid).unwrap();
+        assert_eq!(r#"{"width":4,"height":3,"cells":"bo$2bo$3o!\n"}"#, json);
+        assert_eq!(grid, serde_json::from_str(&json).unwrap());
+
+        assert!(serde_json::from_str::<Grid>(r#"{"width":1,"height":1,"cells":"o$x!"}"#).is_err());
+    }
+
+    #[test]
+    fn test_too_large() {
+        for json in [
+            r#"{"width":4294967296,"height":4294967296,"cells":"!"}"#,
+            r#"{"width":65536,"height":65536,"cells":"!"}"#,
+        ] {
+            let Err(err) = serde_json::from_str::<Grid>(json) else {
+                panic!("parsed {json}");
+            };
+            assert!(err.to_string().contains("too large"), "{err}");
+        }
+
+        let json = r#"{"width":65536,"height":65536,"rule":"B3/S23","edge_behavior":"wrap","generation":0,"cells":"!"}"#;
+        assert!(serde_json::from_str::<Universe>(json).is_err());
+    }
+
+    #[test]
+    fn test_enums_json() {
+        assert_eq!(r#""alive""#, serde_json::to_string(&Cell::Alive).unwrap());
+        assert_eq!(
+            EdgeBehavior::Dead,
+            serde_json::from_str(r#""dead""#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_universe_round_trip() {
+        let mut universe = Universe::new(16, 12);
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        universe.set_edge_behavior(EdgeBehavior::Alive);
+        universe.tick();
+
+        let json = serde_json::to_string(&universe).unwrap();
+        let restored: Universe = serde_json::from_str(&json).unwrap();
+        assert_eq!(universe.get_cells(), restored.get_cells());
+        assert_eq!((16, 12), (restored.width(), restored.height()));
+        assert_eq!(EdgeBehavior::Alive, restored.edge_behavior());
+        assert_eq!(1, restored.generation());
+        assert!(restored.get_overlay().is_none());
+
+        universe.set_overlay_enabled(true);
+        universe.toggle_mark(2, 2);
+        universe.toggle_boundary(5, 5);
+        let json = serde_json::to_string(&universe).unwrap();
+        let restored: Universe = serde_json::from_str(&json).unwrap();
+        assert_eq!(universe.get_cells(), restored.get_cells());
+        assert_eq!(universe.get_overlay(), restored.get_overlay());
+    }
+
+    #[test]
+    fn test_universe_unsupported_rule() {
+        let json = r#"{"width":2,"height":2,"rule":"B36/S23","edge_behavior":"wrap","generation":0,"cells":"!"}"#;
+        let Err(err) = serde_json::from_str::<Universe>(json) else {
+            panic!("parsed a universe with an unsupported rule");
+        };
+        assert!(err.to_string().contains("B36/S23"), "{err}");
+    }
+}
@@ -0,0 +1,306 @@
+//! Compact URL-safe encoding of patterns for sharing as links
+//!
+//! A share string is unpadded base64url of a version byte followed by
+//! DEFLATE compressed fields:
+//!
+//! | bytes | contents                              |
+//! |-------|---------------------------------------|
+//! | 1     | edge behavior                         |
+//! | 1 + n | rule length and rule in B/S notation  |
+//! | 4, 4  | width and height, little endian       |
+//! | rest  | the cells as RLE without a header     |
+
+#[cfg(feature = "wasm")]
+use js_sys::TypeError;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{prelude::*, JsValue};
+
+use std::fmt::Display;
+
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec_with_limit};
+
+use crate::{parse_rle, write_rle_body, EdgeBehavior, Grid, ParseError, Universe, RULE};
+
+/// Current share string format version.
+pub const SHARE_VERSION: u8 = 1;
+
+/// Largest decompressed payload and number of cells accepted, to bound memory
+/// use of untrusted links.
+const MAX_PAYLOAD: usize = 64 * 1024 * 1024;
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShareError {
+    /// Not valid unpadded base64url
+    Base64,
+    /// Written by a newer version
+    UnsupportedVersion(u8),
+    /// Compressed data is corrupt or too large
+    Decompress,
+    /// A field has an impossible value
+    Invalid(&'static str),
+    /// The universe only evolves by [`RULE`]
+    UnsupportedRule(String),
+    /// A field is too long for the format to encode
+    TooLarge(&'static str),
+    Parse(ParseError),
+}
+
+impl std::error::Error for ShareError {}
+
+impl Display for ShareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShareError::Base64 => write!(f, "share string is not base64url"),
+            ShareError::UnsupportedVersion(v) => write!(f, "unsupported share version {v}"),
+            ShareError::Decompress => write!(f, "share string is corrupt"),
+            ShareError::Invalid(field) => write!(f, "invalid {field} in share string"),
+            ShareError::UnsupportedRule(r) => write!(f, "unsupported rule {r:?}"),
+            ShareError::TooLarge(field) => write!(f, "{field} too large to share"),
+            ShareError::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+/// A pattern with the settings needed to run it as its author did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedPattern {
+    pub grid: Grid,
+    /// Rule in B/S notation
+    pub rule: String,
+    pub edge_behavior: EdgeBehavior,
+}
+
+impl SharedPattern {
+    /// Encodes the pattern as a URL-safe string.
+    ///
+    /// Fails if the rule is over 255 bytes or the grid over `u32::MAX` cells
+    /// wide or high.
+    pub fn encode(&self) -> Result<String, ShareError> {
+        let rule_len = u8::try_from(self.rule.len()).map_err(|_| ShareError::TooLarge("rule"))?;
+        let width = u32::try_from(self.grid.width).map_err(|_| ShareError::TooLarge("width"))?;
+        let height = u32::try_from(self.grid.height).map_err(|_| ShareError::TooLarge("height"))?;
+        let mut payload = vec![self.edge_behavior as u8, rule_len];
+        payload.extend_from_slice(self.rule.as_bytes());
+        payload.extend_from_slice(&width.to_le_bytes());
+        payload.extend_from_slice(&height.to_le_bytes());
+        payload.extend_from_slice(write_rle_body(&self.grid).as_bytes());
+
+        let mut bytes = vec![SHARE_VERSION];
+        bytes.extend(compress_to_vec(&payload, 9));
+        Ok(base64url_encode(&bytes))
+    }
+
+    /// Decodes a string made by [`encode`](Self::encode).
+    pub fn decode(share: &str) -> Result<SharedPattern, ShareError> {
+        let bytes = base64url_decode(share)?;
+        let (&version, compressed) = bytes.split_first().ok_or(ShareError::Invalid("version"))?;
+        if version != SHARE_VERSION {
+            return Err(ShareError::UnsupportedVersion(version));
+        }
+        let payload = decompress_to_vec_with_limit(compressed, MAX_PAYLOAD)
+            .map_err(|_| ShareError::Decompress)?;
+
+        let [edge, rule_len, rest @ ..] = payload.as_slice() else {
+            return Err(ShareError::Invalid("header"));
+        };
+        let edge_behavior = match edge {
+            0 => EdgeBehavior::Wrap,
+            1 => EdgeBehavior::Dead,
+            2 => EdgeBehavior::Alive,
+            _ => return Err(ShareError::Invalid("edge behavior")),
+        };
+        if rest.len() < *rule_len as usize {
+            return Err(ShareError::Invalid("rule"));
+        }
+        let (rule, rest) = rest.split_at(*rule_len as usize);
+        let rule = std::str::from_utf8(rule).map_err(|_| ShareError::Invalid("rule"))?;
+        let [w0, w1, w2, w3, h0, h1, h2, h3, body @ ..] = rest else {
+            return Err(ShareError::Invalid("dimensions"));
+        };
+        let width = u32::from_le_bytes([*w0, *w1, *w2, *w3]);
+        let height = u32::from_le_bytes([*h0, *h1, *h2, *h3]);
+        if width as u64 * height as u64 > MAX_PAYLOAD as u64 {
+            return Err(ShareError::Invalid("dimensions"));
+        }
+        let body = std::str::from_utf8(body).map_err(|_| ShareError::Invalid("pattern"))?;
+        let grid =
+            parse_rle(&format!("x = {width}, y = {height}\n{body}")).map_err(ShareError::Parse)?;
+
+        Ok(SharedPattern {
+            grid,
+            rule: rule.to_owned(),
+            edge_behavior,
+        })
+    }
+}
+
+/// Sharing methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Encodes the whole universe and its edge behavior as a URL-safe string.
+    pub fn to_share_string(&self) -> String {
+        SharedPattern {
+            grid: self.region(0, 0, self.width, self.height),
+            rule: RULE.to_owned(),
+            edge_behavior: self.edge_behavior,
+        }
+        .encode()
+        .expect("universes and their rule fit in share strings")
+    }
+
+    /// Loads a share string, keeping the history budget, activity tracking
+    /// and clipboard.
+    #[cfg(feature = "wasm")]
+    pub fn reset_from_share_string(&mut self, share: &str) -> Result<(), JsValue> {
+        let universe =
+            Self::from_share_string(share).map_err(|e| TypeError::new(e.to_string().as_ref()))?;
+        self.replace_with(universe);
+        Ok(())
+    }
+}
+
+impl Universe {
+    /// Creates a universe from a string made by
+    /// [`to_share_string`](Self::to_share_string).
+    pub fn from_share_string(share: &str) -> Result<Universe, ShareError> {
+        let shared = SharedPattern::decode(share)?;
+        if shared.rule != RULE {
+            return Err(ShareError::UnsupportedRule(shared.rule));
+        }
+        let Grid {
+            width,
+            height,
+            cells,
+        } = shared.grid;
+        let width = width.try_into().map_err(|_| ShareError::Invalid("width"))?;
+        let height = height
+            .try_into()
+            .map_err(|_| ShareError::Invalid("height"))?;
+        let mut universe = Universe::of_cells(width, height, cells);
+        universe.edge_behavior = shared.edge_behavior;
+        Ok(universe)
+    }
+}
+
+/// Unpadded base64 with the URL and filename safe alphabet of RFC 4648.
+fn base64url_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64URL[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+        }
+    }
+    out
+}
+
+fn base64url_decode(s: &str) -> Result<Vec<u8>, ShareError> {
+    let digits = s
+        .bytes()
+        .map(|c| {
+            BASE64URL
+                .iter()
+                .position(|&d| d == c)
+                .map(|d| d as u32)
+                .ok_or(ShareError::Base64)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut out = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(ShareError::Base64);
+        }
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |n, (i, &d)| n | d << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base64url() {
+        for (bytes, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg"),
+            (&[0xFB, 0xFF], "-_8"),
+        ] {
+            assert_eq!(encoded, base64url_encode(bytes));
+            assert_eq!(Ok(bytes.to_vec()), base64url_decode(encoded));
+        }
+        assert_eq!(Err(ShareError::Base64), base64url_decode("Zm9v="));
+        assert_eq!(Err(ShareError::Base64), base64url_decode("Zm9vY"));
+    }
+
+    #[test]
+    fn test_share_round_trip() {
+        let mut universe = Universe::new(64, 64);
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        universe.set_edge_behavior(EdgeBehavior::Alive);
+
+        let share = universe.to_share_string();
+        assert!(share.len() < 40, "{share}");
+        assert!(share
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+
+        let shared = Universe::from_share_string(&share).unwrap();
+        assert_eq!(universe.get_cells(), shared.get_cells());
+        assert_eq!(EdgeBehavior::Alive, shared.edge_behavior());
+    }
+
+    #[test]
+    fn test_share_errors() {
+        let pattern = SharedPattern {
+            grid: Grid::new(2, 2),
+            rule: "B36/S23".to_owned(),
+            edge_behavior: EdgeBehavior::Wrap,
+        };
+        let share = pattern.encode().unwrap();
+        assert_eq!(Ok(pattern.clone()), SharedPattern::decode(&share));
+        assert_eq!(
+            Some(ShareError::UnsupportedRule("B36/S23".to_owned())),
+            Universe::from_share_string(&share).err()
+        );
+
+        // the longest rule whose length fits the format
+        let long = SharedPattern {
+            rule: "S".repeat(255),
+            ..pattern.clone()
+        };
+        let share = long.encode().unwrap();
+        assert_eq!(Ok(long), SharedPattern::decode(&share));
+        let too_long = SharedPattern {
+            rule: "S".repeat(256),
+            ..pattern
+        };
+        assert_eq!(Err(ShareError::TooLarge("rule")), too_long.encode());
+
+        let newer = base64url_encode(&[SHARE_VERSION + 1]);
+        assert_eq!(
+            Some(ShareError::UnsupportedVersion(SHARE_VERSION + 1)),
+            SharedPattern::decode(&newer).err()
+        );
+        let corrupt = base64url_encode(&[SHARE_VERSION, 0xFF, 0xFF]);
+        assert_eq!(
+            Some(ShareError::Decompress),
+            SharedPattern::decode(&corrupt).err()
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Compact binary snapshots of a universe's state
+//!
+//! A snapshot holds everything needed to continue a simulation: dimensions,
+//! rule, edge behavior, generation and cells, plus the LifeHistory overlay if
+//! enabled. The undo history is not saved.
+//!
+//! All integers are little endian:
+//!
+//! | bytes | contents                                       |
+//! |-------|------------------------------------------------|
+//! | 4     | magic, `GOLS`                                  |
+//! | 1     | format version, [`SNAPSHOT_VERSION`]           |
+//! | 4, 4  | width and height                               |
+//! | 1 + n | rule length and rule in B/S notation           |
+//! | 1     | edge behavior                                  |
+//! | 4     | generation                                     |
+//! | 1     | flags, bit 0 set if the overlay follows        |
+//! | 4 + n | length and PackBits compressed cell bitset     |
+//! | 4 + n | length and PackBits compressed overlay, if set |
+
+#[cfg(feature = "wasm")]
+use js_sys::TypeError;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{prelude::*, JsValue};
+
+use std::fmt::Display;
+
+use crate::history::{pack_cells, unpack_cells};
+use crate::{EdgeBehavior, Universe, RULE};
+
+/// First bytes of every snapshot.
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"GOLS";
+
+/// Current snapshot format version.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+const FLAG_OVERLAY: u8 = 1 << 0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// Not a snapshot
+    BadMagic,
+    /// Written by a newer version
+    UnsupportedVersion(u8),
+    /// The universe only evolves by [`RULE`]
+    UnsupportedRule(String),
+    /// Ended before all fields were read
+    Truncated,
+    /// A field has an impossible value
+    Invalid(&'static str),
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "not a snapshot"),
+            SnapshotError::UnsupportedVersion(v) => {
+                write!(f, "unsupported snapshot version {v}")
+            }
+            SnapshotError::UnsupportedRule(r) => write!(f, "unsupported rule {r:?}"),
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::Invalid(field) => write!(f, "invalid {field} in snapshot"),
+        }
+    }
+}
+
+/// Snapshot methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Saves the universe's state as a binary snapshot.
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(SNAPSHOT_MAGIC);
+        out.push(SNAPSHOT_VERSION);
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.push(RULE.len() as u8);
+        out.extend_from_slice(RULE.as_bytes());
+        out.push(self.edge_behavior as u8);
+        out.extend_from_slice(&self.generation.to_le_bytes());
+
+        let overlay = self.get_overlay();
+        out.push(if overlay.is_some() { FLAG_OVERLAY } else { 0 });
+        write_section(&mut out, &pack_bits(&pack_cells(&self.cells)));
+        if let Some(overlay) = overlay {
+            write_section(&mut out, &pack_bits(overlay));
+        }
+        out
+    }
+
+    /// Restores a snapshot, keeping the history budget, activity tracking
+    /// and clipboard.
+    #[cfg(feature = "wasm")]
+    pub fn reset_from_snapshot(&mut self, snapshot: &[u8]) -> Result<(), JsValue> {
+        let universe =
+            Self::from_snapshot(snapshot).map_err(|e| TypeError::new(e.to_string().as_ref()))?;
+        self.replace_with(universe);
+        Ok(())
+    }
+}
+
+impl Universe {
+    /// Loads a universe from a snapshot made by [`to_snapshot`](Self::to_snapshot).
+    pub fn from_snapshot(snapshot: &[u8]) -> Result<Universe, SnapshotError> {
+        let mut reader = Reader(snapshot);
+        if reader.take(SNAPSHOT_MAGIC.len())? != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = reader.u8()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+        let len = width
+            .checked_mul(height)
+            .ok_or(SnapshotError::Invalid("dimensions"))? as usize;
+
+        let rule_len = reader.u8()? as usize;
+        let rule = String::from_utf8_lossy(reader.take(rule_len)?);
+        if rule != RULE {
+            return Err(SnapshotError::UnsupportedRule(rule.into_owned()));
+        }
+        let edge_behavior = match reader.u8()? {
+            0 => EdgeBehavior::Wrap,
+            1 => EdgeBehavior::Dead,
+            2 => EdgeBehavior::Alive,
+            _ => return Err(SnapshotError::Invalid("edge behavior")),
+        };
+        let generation = reader.u32()?;
+        let flags = reader.u8()?;
+
+        let packed = unpack_bits(reader.section()?)?;
+        if packed.len() != len.div_ceil(8) {
+            return Err(SnapshotError::Invalid("cells"));
+        }
+        let cells = unpack_cells(&packed, len).collect();
+
+        let mut universe = Universe::of_cells(width, height, cells);
+        universe.edge_behavior = edge_behavior;
+        universe.generation = generation;
+        if flags & FLAG_OVERLAY != 0 {
+            let overlay = unpack_bits(reader.section()?)?;
+            if overlay.len() != len {
+                return Err(SnapshotError::Invalid("overlay"));
+            }
+            universe.set_overlay(overlay);
+        }
+        Ok(universe)
+    }
+}
+
+fn write_section(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.0.len() < n {
+            return Err(SnapshotError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn section(&mut self) -> Result<&'a [u8], SnapshotError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+/// Compresses bytes with the PackBits run length encoding.
+///
+/// Each header byte `n` is followed by `n + 1` literal bytes if `n < 128`, or
+/// by a single byte repeated `257 - n` times if `n > 128`.
+pub(crate) fn pack_bits(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(128)
+            .take_while(|&&b| b == data[i])
+            .count();
+        if run >= 2 {
+            out.push((257 - run) as u8);
+            out.push(data[i]);
+            i += run;
+            continue;
+        }
+
+        // literals until the next run of at least 2
+        let start = i;
+        while i < data.len() && i - start < 128 {
+            if i + 1 < data.len() && data[i] == data[i + 1] {
+                break;
+            }
+            i += 1;
+        }
+        out.push((i - start - 1) as u8);
+        out.extend_from_slice(&data[start..i]);
+    }
+    out
+}
+
+/// Decompresses bytes compressed by [`pack_bits`].
+pub(crate) fn unpack_bits(data: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+    let mut out = Vec::new();
+    let mut reader = Reader(data);
+    while !reader.0.is_empty() {
+        match reader.u8()? {
+            n @ 0..=127 => out.extend_from_slice(reader.take(n as usize + 1)?),
+            128 => {}
+            n => {
+                let byte = reader.u8()?;
+                out.extend(std::iter::repeat_n(byte, 257 - n as usize));
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Cell;
+
+    #[test]
+    fn test_pack_bits_round_trip() {
+        for data in [
+            vec![],
+            vec![7],
+            vec![0; 1000],
+            vec![1, 2, 3, 3, 3, 4, 5, 5],
+            (0..=255).collect(),
+        ] {
+            let packed = pack_bits(&data);
+            assert_eq!(data, unpack_bits(&packed).unwrap(), "{data:?}");
+        }
+        assert_eq!(vec![0x83, 0], pack_bits(&[0; 126]));
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut universe = Universe::new(50, 40);
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        universe.set_edge_behavior(EdgeBehavior::Dead);
+        universe.set_overlay_enabled(true);
+        universe.toggle_boundary(10, 10);
+        for _ in 0..5 {
+            universe.tick();
+        }
+
+        let snapshot = universe.to_snapshot();
+        // a mostly empty board, overlay included, compresses below one bit
+        // per cell
+        assert!(snapshot.len() < 50 * 40 / 8, "{}", snapshot.len());
+
+        let restored = Universe::from_snapshot(&snapshot).unwrap();
+        assert_eq!(universe.get_cells(), restored.get_cells());
+        assert_eq!(universe.get_overlay(), restored.get_overlay());
+        assert_eq!((50, 40), (restored.width(), restored.height()));
+        assert_eq!(EdgeBehavior::Dead, restored.edge_behavior());
+        assert_eq!(5, restored.generation());
+    }
+
+    #[test]
+    fn test_snapshot_errors() {
+        let snapshot = Universe::new(4, 4).to_snapshot();
+        assert_eq!(
+            Some(SnapshotError::BadMagic),
+            Universe::from_snapshot(b"RLE!").err()
+        );
+        assert_eq!(
+            Some(SnapshotError::Truncated),
+            Universe::from_snapshot(&snapshot[..snapshot.len() - 1]).err()
+        );
+
+        let mut newer = snapshot.clone();
+        newer[4] = SNAPSHOT_VERSION + 1;
+        assert_eq!(
+            Some(SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1)),
+            Universe::from_snapshot(&newer).err()
+        );
+
+        let restored = Universe::from_snapshot(&snapshot).unwrap();
+        assert!(restored.get_cells().iter().all(|&c| c == Cell::Dead));
+        assert!(restored.get_overlay().is_none());
+    }
+}
@@ -0,0 +1,384 @@
+//! Rotations, reflections, translation and cropping of patterns
+//!
+//! The eight symmetries of the square are applied to a whole [`Grid`], or to a
+//! rectangular region of a [`Universe`] for editing in place.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Grid, Universe};
+
+/// One of the eight symmetries of the square.
+///
+/// Rotations are clockwise.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirror left to right
+    FlipHorizontal,
+    /// Mirror top to bottom
+    FlipVertical,
+    /// Mirror across the main diagonal
+    Transpose,
+    /// Mirror across the anti-diagonal
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    /// The symmetry that undoes this one.
+    pub fn inverse(self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => other,
+        }
+    }
+
+    /// Whether width and height are exchanged.
+    pub fn swaps_axes(self) -> bool {
+        matches!(
+            self,
+            Symmetry::Rotate90
+                | Symmetry::Rotate270
+                | Symmetry::Transpose
+                | Symmetry::AntiTranspose
+        )
+    }
+
+    /// Maps `(x, y)` in a `width` by `height` area to its transformed position.
+    pub(crate) fn map(self, (x, y): (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        let (w, h) = (width - 1, height - 1);
+        match self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (h - y, x),
+            Symmetry::Rotate180 => (w - x, h - y),
+            Symmetry::Rotate270 => (y, w - x),
+            Symmetry::FlipHorizontal => (w - x, y),
+            Symmetry::FlipVertical => (x, h - y),
+            Symmetry::Transpose => (y, x),
+            Symmetry::AntiTranspose => (h - y, w - x),
+        }
+    }
+}
+
+impl Grid {
+    /// Creates a grid of dead cells.
+    pub fn new(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            cells: vec![Cell::Dead; width * height],
+        }
+    }
+
+    /// Returns a rotated and/or reflected copy of the grid.
+    pub fn transform(&self, symmetry: Symmetry) -> Grid {
+        let (width, height) = if symmetry.swaps_axes() {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let mut grid = Grid::new(width, height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let dest = symmetry.map((x, y), self.width, self.height);
+                grid[dest] = self[(x, y)];
+            }
+        }
+        grid
+    }
+
+    /// Returns a copy with the contents shifted by `dx` columns and `dy` rows.
+    ///
+    /// The dimensions stay the same: cells shifted past the edge are dropped
+    /// and the space left behind is dead.
+    pub fn translate(&self, dx: isize, dy: isize) -> Grid {
+        let mut grid = Grid::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy))
+                else {
+                    continue;
+                };
+                if nx < self.width && ny < self.height {
+                    grid[(nx, ny)] = self[(x, y)];
+                }
+            }
+        }
+        grid
+    }
+
+    /// Smallest rectangle containing all alive cells, as `(x, y, width, height)`.
+    ///
+    /// Returns `None` if there are no alive cells.
+    pub fn bounding_box(&self) -> Option<(usize, usize, usize, usize)> {
+        let mut alive = self
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, &c)| c == Cell::Alive)
+            .map(|(i, _)| (i % self.width, i / self.width));
+        let (x, y) = alive.next()?;
+        let (min_x, min_y, max_x, max_y) =
+            alive.fold((x, y, x, y), |(min_x, min_y, max_x, max_y), (x, y)| {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            });
+        Some((min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+    }
+
+    /// Returns the `width` by `height` area starting at `(x, y)`.
+    ///
+    /// Parts of the area outside of the grid are dead.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Grid {
+        let mut grid = Grid::new(width, height);
+        for dy in 0..height.min(self.height.saturating_sub(y)) {
+            for dx in 0..width.min(self.width.saturating_sub(x)) {
+                grid[(dx, dy)] = self[(x + dx, y + dy)];
+            }
+        }
+        grid
+    }
+
+    /// Returns the grid cropped to its [`bounding_box`](Self::bounding_box).
+    ///
+    /// A grid without alive cells crops to 0x0.
+    pub fn crop_to_bounding_box(&self) -> Grid {
+        match self.bounding_box() {
+            Some((x, y, width, height)) => self.crop(x, y, width, height),
+            None => Grid::new(0, 0),
+        }
+    }
+}
+
+/// Region editing methods for JS
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Universe {
+    /// Rotates and/or reflects the cells in a region.
+    ///
+    /// The transformed region keeps the same top left corner. If the region is
+    /// not square and the symmetry exchanges width and height, cells outside
+    /// of the original region are overwritten.
+    pub fn transform_region(
+        &mut self,
+        row: u32,
+        col: u32,
+        width: u32,
+        height: u32,
+        symmetry: Symmetry,
+    ) {
+        let grid = self.region(row, col, width, height);
+        let blank = Grid::new(grid.width, grid.height);
+        let transformed = grid.transform(symmetry);
+        let changes = self
+            .region_changes(&blank, row, col)
+            .chain(self.region_changes(&transformed, row, col));
+        self.edit_cells(changes);
+    }
+
+    /// Moves the cells in a region by `d_row` rows and `d_col` columns.
+    ///
+    /// The region is cleared and its contents written at the new position.
+    pub fn translate_region(
+        &mut self,
+        row: u32,
+        col: u32,
+        width: u32,
+        height: u32,
+        d_row: i32,
+        d_col: i32,
+    ) {
+        if self.cells.is_empty() {
+            return;
+        }
+        let grid = self.region(row, col, width, height);
+        let blank = Grid::new(grid.width, grid.height);
+        let new_row = (row as i64 + d_row as i64).rem_euclid(self.height as i64) as u32;
+        let new_col = (col as i64 + d_col as i64).rem_euclid(self.width as i64) as u32;
+        let changes = self
+            .region_changes(&blank, row, col)
+            .chain(self.region_changes(&grid, new_row, new_col));
+        self.edit_cells(changes);
+    }
+
+    /// Smallest rectangle of alive cells within a region, as
+    /// `[row, col, width, height]`.
+    ///
+    /// Returns an empty array if the region has no alive cells.
+    pub fn region_bounding_box(&self, row: u32, col: u32, width: u32, height: u32) -> Vec<u32> {
+        let grid = self.region(row, col, width, height);
+        match grid.bounding_box() {
+            Some((x, y, w, h)) => vec![
+                wrap(row, y, self.height) as u32,
+                wrap(col, x, self.width) as u32,
+                w as u32,
+                h as u32,
+            ],
+            None => Vec::new(),
+        }
+    }
+}
+
+impl Universe {
+    /// Copies the cells in a region into a grid.
+    ///
+    /// Regions extending past the edges wrap around to the other side.
+    /// Returns an empty grid if the universe has no cells.
+    pub fn region(&self, row: u32, col: u32, width: u32, height: u32) -> Grid {
+        if self.cells.is_empty() {
+            return Grid::new(0, 0);
+        }
+        let mut grid = Grid::new(width as usize, height as usize);
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let idx =
+                    wrap(row, y, self.height) * self.width as usize + wrap(col, x, self.width);
+                grid[(x, y)] = self.cells[idx];
+            }
+        }
+        grid
+    }
+
+    /// Cell indices and states to overwrite the cells at an offset with a grid.
+    ///
+    /// Regions extending past the edges wrap around to the other side.
+    fn region_changes<'a>(
+        &self,
+        grid: &'a Grid,
+        row: u32,
+        col: u32,
+    ) -> impl Iterator<Item = (usize, Cell)> + 'a {
+        let (width, height) = (self.width, self.height);
+        (0..grid.height).flat_map(move |y| {
+            (0..grid.width).map(move |x| {
+                let idx = wrap(row, y, height) * width as usize + wrap(col, x, width);
+                (idx, grid[(x, y)])
+            })
+        })
+    }
+}
+
+/// `start + offset` wrapped around to below `size`, without overflowing.
+fn wrap(start: u32, offset: usize, size: u32) -> usize {
+    ((start as u64 + offset as u64) % size as u64) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn grid(s: &str) -> Grid {
+        crate::parse_plaintext(s).unwrap()
+    }
+
+    // .O.
+    // ..O
+    // OOO
+    const GLIDER: &str = "!\n.O.\n..O\nOOO\n";
+    // ..
+    // OO
+    // O.
+    const L: &str = "!\n..\nOO\nO.\n";
+
+    #[test]
+    fn test_rotations() {
+        let l = grid(L);
+        assert_eq!("OO.\n.O.\n", l.transform(Symmetry::Rotate90).to_string());
+        assert_eq!(".O\nOO\n..\n", l.transform(Symmetry::Rotate180).to_string());
+        assert_eq!(".O.\n.OO\n", l.transform(Symmetry::Rotate270).to_string());
+    }
+
+    #[test]
+    fn test_reflections() {
+        let l = grid(L);
+        assert_eq!(
+            "..\nOO\n.O\n",
+            l.transform(Symmetry::FlipHorizontal).to_string()
+        );
+        assert_eq!(
+            "O.\nOO\n..\n",
+            l.transform(Symmetry::FlipVertical).to_string()
+        );
+        assert_eq!(".OO\n.O.\n", l.transform(Symmetry::Transpose).to_string());
+        assert_eq!(
+            ".O.\nOO.\n",
+            l.transform(Symmetry::AntiTranspose).to_string()
+        );
+    }
+
+    #[test]
+    fn test_inverse() {
+        let l = grid(L);
+        for symmetry in Symmetry::ALL {
+            assert_eq!(l, l.transform(symmetry).transform(symmetry.inverse()));
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        let glider = grid(GLIDER);
+        assert_eq!("...\n.O.\n..O\n", glider.translate(0, 1).to_string());
+        assert_eq!(".O.\nOO.\n...\n", glider.translate(-1, -1).to_string());
+    }
+
+    #[test]
+    fn test_crop_to_bounding_box() {
+        let g = grid("!\n.....\n..O..\n...O.\n.....\n");
+        assert_eq!(Some((2, 1, 2, 2)), g.bounding_box());
+        assert_eq!("O.\n.O\n", g.crop_to_bounding_box().to_string());
+        assert_eq!(None, Grid::new(3, 3).bounding_box());
+    }
+
+    #[test]
+    fn test_transform_region() {
+        let mut universe = Universe::new(5, 5);
+        universe.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+        universe.transform_region(1, 1, 3, 3, Symmetry::FlipVertical);
+        assert_eq!("OOO\n..O\n.O.\n", universe.region(1, 1, 3, 3).to_string());
+        assert_eq!(vec![1, 1, 3, 3], universe.region_bounding_box(0, 0, 5, 5));
+    }
+
+    #[test]
+    fn test_region_edges() {
+        let mut universe = Universe::new(4, 4);
+        universe.set_cells(&[(3, 3)]);
+        let max = u32::MAX;
+        assert_eq!("O.\n..\n", universe.region(max, max, 2, 2).to_string());
+        assert_eq!(
+            vec![3, 3, 1, 1],
+            universe.region_bounding_box(max, max, 2, 2)
+        );
+        universe.transform_region(max, max, 2, 2, Symmetry::Rotate180);
+        assert_eq!(vec![0, 0, 1, 1], universe.region_bounding_box(0, 0, 4, 4));
+
+        let mut empty = Universe::new(0, 0);
+        assert_eq!(Grid::new(0, 0), empty.region(1, 1, 3, 3));
+        assert_eq!(Vec::<u32>::new(), empty.region_bounding_box(1, 1, 3, 3));
+        empty.transform_region(1, 1, 3, 3, Symmetry::Rotate90);
+        empty.translate_region(1, 1, 3, 3, 1, 1);
+    }
+
+    #[test]
+    fn test_translate_region_wraps() {
+        let mut universe = Universe::new(4, 4);
+        universe.set_cells(&[(0, 0), (0, 1)]);
+        universe.translate_region(0, 0, 2, 1, -1, 3);
+        assert_eq!(vec![3, 3, 2, 1], universe.region_bounding_box(3, 3, 4, 4));
+        assert_eq!(Cell::Alive, universe.get_cells()[15]);
+        assert_eq!(Cell::Alive, universe.get_cells()[12]);
+    }
+}
@@ -15,7 +15,7 @@ mod timer {
 
     impl<'a> Timer<'a> {
         pub fn new(name: &'a str) -> Timer<'a> {
-            let start = log_enabled!(TIMER_LEVEL).then(|| Instant::now());
+            let start = log_enabled!(TIMER_LEVEL).then(Instant::now);
             Timer { name, start }
         }
     }
@@ -0,0 +1,114 @@
+//! Tests of the `life` command line tool.
+
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Output, Stdio};
+
+fn life(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_life"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // commands that fail early exit without reading their input
+    let written = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    if let Err(e) = written {
+        assert_eq!(ErrorKind::BrokenPipe, e.kind(), "{e}");
+    }
+    child.wait_with_output().unwrap()
+}
+
+const GLIDER: &str = "x = 8, y = 8\nbo$2bo$3o!\n";
+
+#[test]
+fn test_run_and_convert() {
+    let output = life(&["run", "-g", "4", "--format=cells", "-"], GLIDER);
+    assert!(output.status.success());
+    let expected = "!\n........\n..O.....\n...O....\n.OOO....\n";
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .starts_with(expected),
+        "moved one cell down and right"
+    );
+
+    let share = life(&["convert", "-f", "share", "-"], GLIDER);
+    let share = String::from_utf8(share.stdout).unwrap();
+    let rle = life(&["convert", "-"], &share);
+    assert_eq!(
+        "x = 8, y = 8, rule = B3/S23\nbo$2bo$3o!\n",
+        String::from_utf8(rle.stdout).unwrap()
+    );
+}
+
+#[test]
+fn test_info() {
+    let output = life(&["info", "-"], GLIDER);
+    let info = String::from_utf8(output.stdout).unwrap();
+    assert!(info.contains("population: 5\n"), "{info}");
+    assert!(info.contains("period: spaceship, period 4"), "{info}");
+}
+
+#[test]
+fn test_errors() {
+    let output = life(&["run", "--bogus", "-"], GLIDER);
+    assert!(!output.status.success());
+    assert_eq!(
+        "life: unknown option --bogus\n",
+        String::from_utf8(output.stderr).unwrap()
+    );
+}
+
+#[test]
+fn test_lenient() {
+    let overflowing = "x = 2, y = 1\n3o$o!";
+    let output = life(&["info", "-"], overflowing);
+    assert!(!output.status.success());
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(error.contains("row wider than the pattern"), "{error}");
+
+    let output = life(&["info", "--lenient", "-"], overflowing);
+    let info = String::from_utf8(output.stdout).unwrap();
+    assert!(info.contains("size: 3x2\n"), "{info}");
+    assert!(info.contains("population: 4\n"), "{info}");
+}
+
+#[test]
+fn test_render_gif() {
+    let output = life(&["render", "-f", "gif", "--frames", "4", "-"], GLIDER);
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"GIF89a"));
+
+    let output = life(&["render", "-f", "gif", "--delay", "70000", "-"], GLIDER);
+    assert_eq!(
+        "life: --delay is too large\n",
+        String::from_utf8(output.stderr).unwrap()
+    );
+}
+
+#[test]
+fn test_render_svg() {
+    let output = life(&["render", "-f", "svg", "--labels", "4", "-"], GLIDER);
+    let svg = String::from_utf8(output.stdout).unwrap();
+    assert!(svg.starts_with("<svg"), "{svg}");
+    assert!(svg.contains(">4</text>"), "{svg}");
+}
+
+#[test]
+fn test_image_round_trip() {
+    let path = std::env::temp_dir().join(format!("life-cli-{}.png", std::process::id()));
+    let path = path.to_str().unwrap();
+    let output = life(
+        &["render", "-f", "png", "--grid-lines", "-o", path, "-"],
+        GLIDER,
+    );
+    assert!(output.status.success());
+
+    let rle = life(&["convert", path], "");
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(
+        "x = 8, y = 8, rule = B3/S23\nbo$2bo$3o!\n",
+        String::from_utf8(rle.stdout).unwrap()
+    );
+}
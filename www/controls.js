@@ -1,13 +1,53 @@
 const ctrl = {
     playPauseBtn: document.getElementById("play-pause"),
     stepBtn: document.getElementById("step"),
+    shareBtn: document.getElementById("share"),
+    colorModeSlt: document.getElementById("color-mode"),
+    undoBtn: document.getElementById("undo"),
+    redoBtn: document.getElementById("redo"),
+    stepBackBtn: document.getElementById("step-back"),
+    timelineRange: document.getElementById("timeline"),
+    overlayChk: document.getElementById("overlay"),
+    toolSlt: document.getElementById("tool"),
+    brushSizeNum: document.getElementById("brush-size"),
+    clearOverlayBtn: document.getElementById("clear-overlay"),
+    selectAllBtn: document.getElementById("select-all"),
+    deselectBtn: document.getElementById("deselect"),
+    copyBtn: document.getElementById("copy"),
+    cutBtn: document.getElementById("cut"),
+    pasteBtn: document.getElementById("paste"),
+    clearSelectedBtn: document.getElementById("clear-selected"),
+    invertSelectedBtn: document.getElementById("invert-selected"),
+    randomSelectedBtn: document.getElementById("random-selected"),
+    cropBtn: document.getElementById("crop"),
+    copyRleBtn: document.getElementById("copy-rle"),
+    savePngBtn: document.getElementById("save-png"),
+    saveSvgBtn: document.getElementById("save-svg"),
+    saveGifBtn: document.getElementById("save-gif"),
+    symmetrySlt: document.getElementById("symmetry"),
+    transformBtn: document.getElementById("transform"),
     patternSlt: document.getElementById("pattern-select"),
     resetBtn: document.getElementById("reset"),
     customTxt: document.getElementById("custom-txt"),
     widthEl: document.getElementById("width"),
     heightEl: document.getElementById("height"),
+    anchorSlt: document.getElementById("anchor"),
+    seedTxt: document.getElementById("seed"),
+    densityNum: document.getElementById("density"),
+    soupSymmetrySlt: document.getElementById("soup-symmetry"),
     customUrlTxt: document.getElementById("custom-url"),
     customUrlBtn: document.getElementById("custom-url-submit"),
+    customPasteBtn: document.getElementById("custom-paste"),
+    pasteModeSlt: document.getElementById("paste-mode"),
+    catalogueSearchTxt: document.getElementById("catalogue-search"),
+    catalogueList: document.getElementById("catalogue-list"),
+    catalogueInfo: document.getElementById("catalogue-info"),
+    catalogueLoadBtn: document.getElementById("catalogue-load"),
+    imageFile: document.getElementById("image-file"),
+    imageCellSizeNum: document.getElementById("image-cell-size"),
+    imageOffsetXNum: document.getElementById("image-offset-x"),
+    imageOffsetYNum: document.getElementById("image-offset-y"),
+    imageImportBtn: document.getElementById("image-import"),
     edgeBehaviorSlt: document.getElementById("edge-behavior"),
     speedNum: document.getElementById("speed-num"),
 };
@@ -1,14 +1,23 @@
-import init, { Universe, Cell, EdgeBehavior } from "./pkg/wasm_game_of_life.js";
+import init, { Universe, Cell, Framebuffer, Viewport, EdgeBehavior, Symmetry, PasteMode, Anchor, SoupSymmetry, ColorMode, catalogue } from "./pkg/wasm_game_of_life.js";
 import { Renderer } from "./utils.js";
 import ctrl, { speedToMsPerTick } from "./controls.js";
 
 const { memory }  = await init();
 
-const CELL_SIZE = 5;  // width/height in pixels
-const GRID_COLOR = "#EEEEEE";
-// const GRID_COLOR = "#FFFFFF";
-const DEAD_COLOR = "#FFFFFF";
-const ALIVE_COLOR = "#000000";
+const CANVAS_WIDTH = 800;  // pixels
+const CANVAS_HEIGHT = 600;
+const GRID_COLOR = 0xEEEEEEFF;
+// const GRID_COLOR = 0xFFFFFFFF;
+const DEAD_COLOR = 0xFFFFFFFF;
+const ALIVE_COLOR = 0x000000FF;
+const HISTORY_BUDGET = 32 * 1024 * 1024;  // bytes
+const SELECTION_COLOR = "#3080FF";
+const AUTOSAVE_KEY = "snapshot";
+const SHARE_PARAM = "p";
+const PNG_CELL_SIZE = 8;
+const SVG_LABEL_STEP = 10;
+const GIF_FRAMES = 100;
+const GIF_FRAME_DELAY = 10;  // hundredths of a second
 
 const canvas = document.getElementById("game-of-life-canvas");
 
@@ -26,17 +35,77 @@ function pause() {
     // playPauseBtn.textContent = "⏯";
     renderer.cancel();
     ctrl.stepBtn.enabled = true;
+    autosave();
 }
 
+/** save the board to localStorage, to be restored on the next visit */
+function autosave() {
+    const snapshot = universe.to_snapshot();
+    let binary = "";
+    for (const byte of snapshot) {
+        binary += String.fromCharCode(byte);
+    }
+    try {
+        localStorage.setItem(AUTOSAVE_KEY, btoa(binary));
+    } catch(e) {
+        // storage full or disabled
+        console.error(e);
+    }
+}
+
+/** restore the board saved by autosave, returning whether there was one */
+function restoreAutosave() {
+    const saved = localStorage.getItem(AUTOSAVE_KEY);
+    if (saved === null) {
+        return false;
+    }
+    try {
+        const snapshot = Uint8Array.from(atob(saved), c => c.charCodeAt(0));
+        universe.reset_from_snapshot(snapshot);
+    } catch(e) {
+        console.error(e);
+        localStorage.removeItem(AUTOSAVE_KEY);
+        return false;
+    }
+    syncControls();
+    return true;
+}
+
+/** load the board shared in the URL fragment, returning whether there was one */
+function restoreShareLink() {
+    const params = new URLSearchParams(location.hash.slice(1));
+    const share = params.get(SHARE_PARAM);
+    if (share === null) {
+        return false;
+    }
+    try {
+        universe.reset_from_share_string(share);
+    } catch(e) {
+        console.error(e);
+        return false;
+    }
+    syncControls();
+    return true;
+}
+
+/** update the controls to match a loaded board */
+function syncControls() {
+    ctrl.widthEl.value = universe.width;
+    ctrl.heightEl.value = universe.height;
+    ctrl.edgeBehaviorSlt.value = ["wrap", "dead", "alive"][universe.edge_behavior];
+    ctrl.overlayChk.checked = universe.overlay_enabled;
+}
+
+window.addEventListener("pagehide", autosave);
+
 const isPaused = () => {
     return !renderer.isRunning();
 };
 
 function reset(pattern) {
-    if (pattern !== "custom" && (ctrl.widthEl.value !== width || ctrl.heightEl.value !== height)) {
+    if (pattern !== "custom") {
         // resize based on dimensions input
-        universe.width = ctrl.widthEl.value;
-        universe.height = ctrl.heightEl.value;
+        resize();
     }
 
     switch(pattern) {
@@ -44,7 +113,7 @@ function reset(pattern) {
             universe.reset_fancy();
             break;
         case "random":
-            universe.reset_random();
+            randomSoup();
             break;
         case "blank":
             universe.reset_blank();
@@ -67,9 +136,12 @@ function reset(pattern) {
     // TODO: update or persist edge behavior
     ctrl.edgeBehaviorSlt.value = "wrap";
 
+    // LifeHistory files enable the overlay
+    ctrl.overlayChk.checked = universe.overlay_enabled;
+
     resize_canvas();
-    drawGrid();
     drawCells();
+    updateTimeline();
 }
 
 ctrl.playPauseBtn.addEventListener("click", () => {
@@ -84,12 +156,267 @@ ctrl.stepBtn.addEventListener("click", () => {
     renderer.step();
 });
 
+ctrl.colorModeSlt.addEventListener("change", event => {
+    const mode = ColorMode[event.target.value];
+    // ages and heat are only tracked while they are shown
+    universe.track_activity = mode !== ColorMode.Plain;
+    framebuffer.set_color_mode(mode);
+    drawCells();
+});
+
+ctrl.overlayChk.addEventListener("change", event => {
+    universe.overlay_enabled = event.target.checked;
+    drawCells();
+});
+
+ctrl.clearOverlayBtn.addEventListener("click", () => {
+    universe.clear_overlay_history();
+    universe.clear_marks();
+    drawCells();
+});
+
+function undo() {
+    pause();
+    if (universe.undo()) {
+        drawCellsDelta();
+        updateTimeline();
+    }
+}
+
+function redo() {
+    pause();
+    if (universe.redo()) {
+        drawCellsDelta();
+        updateTimeline();
+    }
+}
+
+ctrl.undoBtn.addEventListener("click", undo);
+ctrl.redoBtn.addEventListener("click", redo);
+
+ctrl.stepBackBtn.addEventListener("click", () => {
+    pause();
+    if (universe.step_back()) {
+        drawCellsDelta();
+        updateTimeline();
+    }
+});
+
+ctrl.timelineRange.addEventListener("input", event => {
+    pause();
+    universe.seek_history(Number(event.target.value));
+    drawCellsDelta();
+});
+
+document.addEventListener("keydown", event => {
+    if (event.target instanceof HTMLInputElement || event.target instanceof HTMLTextAreaElement) {
+        return;
+    }
+    if (event.key === "Delete") {
+        editSelection(() => universe.clear_selected());
+        return;
+    }
+    if (!(event.ctrlKey || event.metaKey)) {
+        return;
+    }
+    switch (event.key.toLowerCase()) {
+        case "z":
+            if (event.shiftKey) {
+                redo();
+            } else {
+                undo();
+            }
+            break;
+        case "c":
+            universe.copy();
+            break;
+        case "x":
+            editSelection(() => universe.cut());
+            break;
+        case "v":
+            pasteClipboard();
+            break;
+        default:
+            return;
+    }
+    event.preventDefault();
+});
+
+ctrl.transformBtn.addEventListener("click", () => {
+    const symmetry = Symmetry[ctrl.symmetrySlt.value];
+    // transform the selection, or the whole board
+    let [row, col, w, h] = universe.has_selection() ? universe.selection() : [0, 0, width, height];
+    if (w !== h && symmetry !== Symmetry.Rotate180
+        && symmetry !== Symmetry.FlipHorizontal && symmetry !== Symmetry.FlipVertical) {
+        // only a square area can be rotated in place
+        w = h = Math.min(w, h);
+    }
+    universe.transform_region(row, col, w, h, symmetry);
+    drawCellsDelta();
+    updateTimeline();
+});
+
+/** run a selection operation, redrawing if anything changed */
+function editSelection(op) {
+    if (op()) {
+        drawCellsDelta();
+        updateTimeline();
+    }
+}
+
+function pasteClipboard() {
+    const [row, col] = universe.has_selection() ? universe.selection() : [0, 0];
+    editSelection(() => universe.paste_clipboard(row, col, PasteMode[ctrl.pasteModeSlt.value]));
+}
+
+ctrl.selectAllBtn.addEventListener("click", () => {
+    universe.select_all();
+    drawCells();
+});
+ctrl.deselectBtn.addEventListener("click", () => {
+    universe.deselect();
+    drawCells();
+});
+ctrl.copyBtn.addEventListener("click", () => universe.copy());
+ctrl.cutBtn.addEventListener("click", () => editSelection(() => universe.cut()));
+ctrl.pasteBtn.addEventListener("click", pasteClipboard);
+ctrl.clearSelectedBtn.addEventListener("click", () => editSelection(() => universe.clear_selected()));
+ctrl.invertSelectedBtn.addEventListener("click", () => editSelection(() => universe.invert_selected()));
+ctrl.randomSelectedBtn.addEventListener("click", () => {
+    const seed = ctrl.seedTxt.value || String(Math.random());
+    const density = Number(ctrl.densityNum.value);
+    const symmetry = SoupSymmetry[ctrl.soupSymmetrySlt.value];
+    editSelection(() => universe.fill_random_selected(seed, density, symmetry));
+});
+ctrl.cropBtn.addEventListener("click", () => {
+    if (universe.crop_to_selection()) {
+        ctrl.widthEl.value = universe.width;
+        ctrl.heightEl.value = universe.height;
+        resize_canvas();
+        drawCells();
+        updateTimeline();
+    }
+});
+ctrl.shareBtn.addEventListener("click", async () => {
+    history.replaceState(null, "", "#" + SHARE_PARAM + "=" + universe.to_share_string());
+    try {
+        await navigator.clipboard.writeText(location.href);
+    } catch(e) {
+        console.error(e);
+    }
+});
+ctrl.copyRleBtn.addEventListener("click", async () => {
+    if (!universe.copy()) {
+        universe.select_all();
+        universe.copy();
+        universe.deselect();
+    }
+    const rle = universe.clipboard_rle();
+    ctrl.customTxt.value = rle;
+    try {
+        await navigator.clipboard.writeText(rle);
+    } catch(e) {
+        console.error(e);
+    }
+});
+
+function exportFramebuffer() {
+    const image = new Framebuffer(PNG_CELL_SIZE, true);
+    image.set_colors(ALIVE_COLOR, DEAD_COLOR, GRID_COLOR);
+    image.set_color_mode(ColorMode[ctrl.colorModeSlt.value]);
+    return image;
+}
+
+function download(bytes, type, name) {
+    const link = document.createElement("a");
+    link.href = URL.createObjectURL(new Blob([bytes], { type }));
+    link.download = name;
+    link.click();
+    URL.revokeObjectURL(link.href);
+}
+
+ctrl.savePngBtn.addEventListener("click", () => {
+    // the selection, or the whole universe
+    const [row, col, width, height] = universe.has_selection()
+        ? universe.selection()
+        : [0, 0, universe.width, universe.height];
+    const image = exportFramebuffer();
+    image.draw_region(universe, row, col, width, height);
+    const png = image.to_png();
+    image.free();
+    if (png === undefined) {
+        console.error("nothing to save");
+        return;
+    }
+    download(png, "image/png", `life-${universe.generation}.png`);
+});
+
+ctrl.saveSvgBtn.addEventListener("click", () => {
+    const image = exportFramebuffer();
+    const svg = image.to_svg(universe, SVG_LABEL_STEP);
+    image.free();
+    download(svg, "image/svg+xml", `life-${universe.generation}.svg`);
+});
+
+ctrl.saveGifBtn.addEventListener("click", () => {
+    const image = exportFramebuffer();
+    try {
+        const gif = image.to_gif(universe, GIF_FRAMES, GIF_FRAME_DELAY);
+        download(gif, "image/gif", `life-${universe.generation}.gif`);
+    } catch(e) {
+        console.error(e);
+    } finally {
+        image.free();
+    }
+});
+
+function updateTimeline() {
+    ctrl.timelineRange.max = universe.history_len();
+    ctrl.timelineRange.value = universe.history_position();
+}
+
 ctrl.patternSlt.addEventListener("change", event => {
     const pattern = event.target.value;
     // customTxt.disabled = (pattern !== "custom");
     reset(pattern);
 });
 
+function randomSoup() {
+    const symmetry = SoupSymmetry[ctrl.soupSymmetrySlt.value];
+    const density = Number(ctrl.densityNum.value);
+    let seed = ctrl.seedTxt.value;
+    if (seed === "") {
+        seed = universe.reset_random();
+        // show the seed so the soup can be recreated
+        ctrl.seedTxt.placeholder = seed;
+        if (symmetry === SoupSymmetry.C1 && density === 0.5) {
+            return;
+        }
+    }
+    if (symmetry === SoupSymmetry.C1) {
+        universe.reset_random_seeded(seed, density);
+    } else {
+        universe.fill_random(0, 0, universe.width, universe.height, seed, density, symmetry);
+    }
+}
+
+function resize() {
+    const newWidth = Number(ctrl.widthEl.value);
+    const newHeight = Number(ctrl.heightEl.value);
+    if (newWidth !== width || newHeight !== height) {
+        universe.resize(newWidth, newHeight, Anchor[ctrl.anchorSlt.value]);
+    }
+}
+
+for (const el of [ctrl.widthEl, ctrl.heightEl]) {
+    el.addEventListener("change", () => {
+        resize();
+        resize_canvas();
+        drawCells();
+        updateTimeline();
+    });
+}
+
 ctrl.edgeBehaviorSlt.addEventListener("change", event => {
     const behavior = event.target.value;
     let b
@@ -139,149 +466,335 @@ ctrl.customUrlBtn.addEventListener("click", async function (event) {
     ctrl.customUrlTxt.setCustomValidity('');
 });
 
+ctrl.customPasteBtn.addEventListener("click", () => {
+    const file = new TextEncoder().encode(ctrl.customTxt.value);
+    const mode = PasteMode[ctrl.pasteModeSlt.value];
+    try {
+        universe.paste_file(file, 0, 0, mode);
+    } catch(e) {
+        console.error(e);
+        ctrl.customTxt.setCustomValidity('Parse error: ' + e);
+        ctrl.customTxt.reportValidity();
+    }
+    ctrl.customTxt.setCustomValidity('');
+    drawCellsDelta();
+    updateTimeline();
+});
+
+let catalogueEntries = [];
+
+function updateCatalogue() {
+    catalogueEntries.forEach(entry => entry.free());
+    catalogueEntries = catalogue(ctrl.catalogueSearchTxt.value);
+    ctrl.catalogueList.replaceChildren(...catalogueEntries.map((entry, i) => {
+        const option = document.createElement("option");
+        option.value = i;
+        option.textContent = `${entry.name} (${entry.category_name})`;
+        return option;
+    }));
+    updateCatalogueInfo();
+}
+
+function updateCatalogueInfo() {
+    const entry = catalogueEntries[ctrl.catalogueList.value];
+    if (!entry) {
+        ctrl.catalogueInfo.textContent = "";
+        return;
+    }
+    let info = entry.description;
+    if (entry.period !== undefined) {
+        info += ` Period ${entry.period}.`;
+    }
+    if (entry.discoverer !== undefined) {
+        info += ` Found by ${entry.discoverer}`;
+        info += entry.year !== undefined ? ` in ${entry.year}.` : ".";
+    }
+    ctrl.catalogueInfo.textContent = info;
+}
+
+function loadCatalogueEntry() {
+    const entry = catalogueEntries[ctrl.catalogueList.value];
+    if (!entry) {
+        return;
+    }
+    universe.reset_from_catalogue(entry);
+    syncControls();
+    resize_canvas();
+    drawCells();
+    updateTimeline();
+}
+
+ctrl.catalogueSearchTxt.addEventListener("input", updateCatalogue);
+ctrl.catalogueList.addEventListener("change", updateCatalogueInfo);
+ctrl.catalogueList.addEventListener("dblclick", loadCatalogueEntry);
+ctrl.catalogueLoadBtn.addEventListener("click", loadCatalogueEntry);
+updateCatalogue();
+
+/** number input value, or undefined if empty */
+function optionalNumber(input) {
+    return input.value === "" ? undefined : Number(input.value);
+}
+
+ctrl.imageImportBtn.addEventListener("click", async () => {
+    const file = ctrl.imageFile.files[0];
+    if (!file) {
+        return;
+    }
+    const image = new Uint8Array(await file.arrayBuffer());
+    try {
+        universe.reset_from_image(
+            image,
+            optionalNumber(ctrl.imageCellSizeNum),
+            optionalNumber(ctrl.imageOffsetXNum),
+            optionalNumber(ctrl.imageOffsetYNum),
+        );
+    } catch(e) {
+        console.error(e);
+        ctrl.imageFile.setCustomValidity('Import error: ' + e);
+        ctrl.imageFile.reportValidity();
+        return;
+    }
+    ctrl.imageFile.setCustomValidity('');
+    syncControls();
+    resize_canvas();
+    drawCells();
+    updateTimeline();
+});
+
 const universe = Universe.new(64, 64);
-universe.reset_fancy();
+universe.history_budget = HISTORY_BUDGET;
+if (!restoreShareLink() && !restoreAutosave()) {
+    universe.reset_fancy();
+}
 
 let width;
 let height;
 
+canvas.width = CANVAS_WIDTH;
+canvas.height = CANVAS_HEIGHT;
+const viewport = new Viewport(CANVAS_WIDTH, CANVAS_HEIGHT);
+
+/** refit the viewport when the universe dimensions change */
 function resize_canvas() {
     const new_width = universe.width;
     const new_height = universe.height;
     if (new_width !== width || new_height !== height) {
         width = new_width;
         height = new_height;
-        canvas.height = (CELL_SIZE + 1) * height + 1;
-        canvas.width = (CELL_SIZE + 1) * width + 1;
+        viewport.fit(width, height);
     }
 }
 
 resize_canvas();
 
-canvas.addEventListener("click", event => {
+/** canvas pixel coordinates of a mouse event */
+function canvasPosition(event) {
     const boundingRect = canvas.getBoundingClientRect();
 
     const scaleX = canvas.width / boundingRect.width;
     const scaleY = canvas.height / boundingRect.height;
 
-    const canvasLeft = (event.clientX - boundingRect.left) * scaleX;
-    const canvasTop = (event.clientY - boundingRect.top) * scaleY;
-
-    const row = Math.min(Math.floor(canvasTop / (CELL_SIZE + 1)), height - 1);
-    const col = Math.min(Math.floor(canvasLeft / (CELL_SIZE + 1)), width - 1);
-
-    universe.toggle_cell(row, col);
-    renderer.redraw();
-});
+    return {
+        x: (event.clientX - boundingRect.left) * scaleX,
+        y: (event.clientY - boundingRect.top) * scaleY,
+    };
+}
 
-function drawGrid() {
-    ctx.beginPath();
-    ctx.strokeStyle = GRID_COLOR;
+// tools that act on a single click, leaving drags to pan
+const CLICK_TOOLS = ["toggle", "mark", "boundary"];
 
-    // Vertical lines;
-    for (let i = 0; i <= width; i++) {
-        ctx.moveTo(i * (CELL_SIZE + 1) + 1, 0);
-        ctx.lineTo(i * (CELL_SIZE + 1) + 1, (CELL_SIZE + 1) * height + 1);
-    }
+let dragStart = null;
+let dragged = false;
+let panning = false;
+// last cell of a pen or eraser stroke
+let strokeEnd = null;
 
-    // Horizontal lines.
-    for (let j = 0; j <= height; j++) {
-        ctx.moveTo(0,                           j * (CELL_SIZE + 1) + 1);
-        ctx.lineTo((CELL_SIZE + 1) * width + 1, j * (CELL_SIZE + 1) + 1);
-    }
+/** cell under a canvas position */
+function cellAt(pos) {
+    return [viewport.row_at(pos.y), viewport.col_at(pos.x)];
+}
 
-    ctx.stroke();
+function strokeTo(pos) {
+    const [row, col] = cellAt(pos);
+    const state = ctrl.toolSlt.value === "pen" ? Cell.Alive : Cell.Dead;
+    const [lastRow, lastCol] = strokeEnd ?? [row, col];
+    universe.draw_stroke(new Int32Array([lastRow, lastCol, row, col]), Number(ctrl.brushSizeNum.value), state);
+    strokeEnd = [row, col];
+    drawCellsDelta();
 }
 
+canvas.addEventListener("mousedown", event => {
+    dragStart = canvasPosition(event);
+    dragged = false;
+    const tool = ctrl.toolSlt.value;
+    panning = event.shiftKey || event.button === 1 || CLICK_TOOLS.includes(tool);
+    if (!panning && (tool === "pen" || tool === "eraser")) {
+        strokeEnd = null;
+        strokeTo(dragStart);
+    }
+});
 
-function getIndex(row, column) {
-    return row * width + column;
+/** select the cells between two canvas positions */
+function selectBetween(a, b) {
+    const top = Math.max(0, Math.min(viewport.row_at(a.y), viewport.row_at(b.y)));
+    const left = Math.max(0, Math.min(viewport.col_at(a.x), viewport.col_at(b.x)));
+    const bottom = Math.max(viewport.row_at(a.y), viewport.row_at(b.y));
+    const right = Math.max(viewport.col_at(a.x), viewport.col_at(b.x));
+    universe.select(top, left, bottom - top + 1, right - left + 1);
 }
 
-function drawCells() {
-    const cellsPtr = universe.cells();
-    const cells = new Uint8Array(memory.buffer, cellsPtr, width * height);
-
-    ctx.beginPath();
+/** outline of the line or rectangle being dragged */
+let shapePreview = null;
 
-    // Alive cells
-    ctx.fillStyle = ALIVE_COLOR;
-    for (let row = 0; row < height; row++) {
-      for (let col = 0; col < width; col++) {
-        const idx = getIndex(row, col);
+canvas.addEventListener("mousemove", event => {
+    if (dragStart === null) {
+        return;
+    }
+    const pos = canvasPosition(event);
+    const dx = pos.x - dragStart.x;
+    const dy = pos.y - dragStart.y;
+    if (!dragged && Math.hypot(dx, dy) < 3) {
+        return;
+    }
+    dragged = true;
+    if (panning) {
+        viewport.pan(dx, dy);
+        dragStart = pos;
+        drawCells();
+        return;
+    }
+    switch (ctrl.toolSlt.value) {
+        case "pen":
+        case "eraser":
+            strokeTo(pos);
+            return;
+        case "select":
+            selectBetween(dragStart, pos);
+            break;
+        default:
+            shapePreview = { from: cellAt(dragStart), to: cellAt(pos) };
+    }
+    drawCells();
+});
 
-        if (cells[idx] !== Cell.Alive) {
-            continue;
+window.addEventListener("mouseup", event => {
+    if (dragStart === null) {
+        return;
+    }
+    if (dragged && !panning) {
+        const [row0, col0] = cellAt(dragStart);
+        const [row1, col1] = cellAt(canvasPosition(event));
+        const [top, left] = [Math.min(row0, row1), Math.min(col0, col1)];
+        const [w, h] = [Math.abs(col1 - col0) + 1, Math.abs(row1 - row0) + 1];
+        switch (ctrl.toolSlt.value) {
+            case "line":
+                universe.draw_line(row0, col0, row1, col1, Cell.Alive);
+                break;
+            case "rect":
+                universe.draw_rect(top, left, w, h, Cell.Alive, false);
+                break;
+            case "filled-rect":
+                universe.draw_rect(top, left, w, h, Cell.Alive, true);
+                break;
         }
+        shapePreview = null;
+        drawCellsDelta();
+        updateTimeline();
+    }
+    dragStart = null;
+});
 
-        ctx.fillRect(
-          col * (CELL_SIZE + 1) + 1,
-          row * (CELL_SIZE + 1) + 1,
-          CELL_SIZE,
-          CELL_SIZE
-        );
-      }
+canvas.addEventListener("click", event => {
+    if (dragged) {
+        return;
+    }
+    const [row, col] = cellAt(canvasPosition(event));
+    if (row < 0 || row >= height || col < 0 || col >= width) {
+        return;
     }
 
-    // Dead cells
-    ctx.fillStyle = DEAD_COLOR;
-    for (let row = 0; row < height; row++) {
-      for (let col = 0; col < width; col++) {
-        const idx = getIndex(row, col);
+    switch (ctrl.toolSlt.value) {
+        case "toggle":
+            universe.toggle_cell(row, col);
+            updateTimeline();
+            break;
+        case "mark":
+            universe.toggle_mark(row, col);
+            ctrl.overlayChk.checked = true;
+            break;
+        case "boundary":
+            universe.toggle_boundary(row, col);
+            ctrl.overlayChk.checked = true;
+            break;
+        case "select":
+            universe.deselect();
+            drawCells();
+            return;
+        default:
+            return;
+    }
+    renderer.redraw();
+});
 
-        if (cells[idx] !== Cell.Dead) {
-            continue;
-        }
+canvas.addEventListener("wheel", event => {
+    event.preventDefault();
+    const { x, y } = canvasPosition(event);
+    viewport.zoom_at(event.deltaY < 0 ? 1 : -1, x, y);
+    drawCells();
+}, { passive: false });
 
-        ctx.fillRect(
-          col * (CELL_SIZE + 1) + 1,
-          row * (CELL_SIZE + 1) + 1,
-          CELL_SIZE,
-          CELL_SIZE
-        );
-      }
-    }
+const framebuffer = new Framebuffer(1, true);
+framebuffer.set_colors(ALIVE_COLOR, DEAD_COLOR, GRID_COLOR);
 
-    ctx.stroke();
+function blit() {
+    const pixels = new Uint8ClampedArray(memory.buffer, framebuffer.pixels(), framebuffer.pixels_len());
+    ctx.putImageData(new ImageData(pixels, framebuffer.width, framebuffer.height), 0, 0);
+    drawSelection();
+    drawShapePreview();
 }
 
-function drawCellsDelta() {
-    const bornPtr = universe.cells_born();
-    const numBorn = universe.cells_born_count();
-    const bornCells = new Uint32Array(memory.buffer, bornPtr, numBorn * 2);
-
-    const diedPtr = universe.cells_died();
-    const numDied = universe.cells_died_count();
-    const diedCells = new Uint32Array(memory.buffer, diedPtr, numDied * 2);
-
-    // Alive cells
-    ctx.fillStyle = ALIVE_COLOR;
-    for (let i = 0; i < numBorn; i++) {
-        const row = bornCells[i*2];
-        const col = bornCells[i*2+1];
-        const idx = getIndex(row, col);
-
-        ctx.fillRect(
-          col * (CELL_SIZE + 1) + 1,
-          row * (CELL_SIZE + 1) + 1,
-          CELL_SIZE,
-          CELL_SIZE
-        );
+function drawShapePreview() {
+    if (shapePreview === null) {
+        return;
     }
+    const [row0, col0] = shapePreview.from;
+    const [row1, col1] = shapePreview.to;
+    ctx.strokeStyle = SELECTION_COLOR;
+    ctx.lineWidth = 1;
+    if (ctrl.toolSlt.value === "line") {
+        // centres of the end cells
+        const half = (viewport.x_of(1) - viewport.x_of(0)) / 2;
+        ctx.beginPath();
+        ctx.moveTo(viewport.x_of(col0) + half, viewport.y_of(row0) + half);
+        ctx.lineTo(viewport.x_of(col1) + half, viewport.y_of(row1) + half);
+        ctx.stroke();
+    } else {
+        const x = viewport.x_of(Math.min(col0, col1));
+        const y = viewport.y_of(Math.min(row0, row1));
+        ctx.strokeRect(x, y, viewport.x_of(Math.max(col0, col1) + 1) - x, viewport.y_of(Math.max(row0, row1) + 1) - y);
+    }
+}
 
-    // Dead cells
-    ctx.fillStyle = DEAD_COLOR;
-    for (let i = 0; i < numDied; i++) {
-        const row = diedCells[i*2];
-        const col = diedCells[i*2+1];
-
-        ctx.fillRect(
-          col * (CELL_SIZE + 1) + 1,
-          row * (CELL_SIZE + 1) + 1,
-          CELL_SIZE,
-          CELL_SIZE
-        );
+function drawSelection() {
+    if (!universe.has_selection()) {
+        return;
     }
+    const [row, col, w, h] = universe.selection();
+    const x = viewport.x_of(col);
+    const y = viewport.y_of(row);
+    ctx.strokeStyle = SELECTION_COLOR;
+    ctx.lineWidth = 2;
+    ctx.strokeRect(x, y, viewport.x_of(col + w) - x, viewport.y_of(row + h) - y);
+}
+
+function drawCells() {
+    framebuffer.draw_viewport(universe, viewport);
+    blit();
+}
+
+function drawCellsDelta() {
+    framebuffer.draw_viewport_delta(universe, viewport);
+    blit();
 }
 
 class GoLRenderer extends Renderer {
@@ -298,6 +811,7 @@ class GoLRenderer extends Renderer {
             default:
                 throw `Unknown method: ${this.method}`;
         }
+        updateTimeline();
     }
 
     redraw() {
@@ -316,6 +830,5 @@ class GoLRenderer extends Renderer {
 
 const renderer = new GoLRenderer({fpsEl: document.getElementById("fps")});
 
-drawGrid();
 drawCells();
 // renderer.loop();